@@ -0,0 +1,128 @@
+//! Finding the config file that applies to each formatted file.
+
+use luau_fmt::{Config, ConfigFile, LoadConfigError};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter, Result as FmtResult},
+    path::{self, Path, PathBuf},
+    sync::Mutex,
+};
+
+use super::files::FilesConfig;
+
+/// Names of config files, in order of priority if a folder has more than one.
+pub const CONFIG_FILE_NAMES: [&str; 2] = ["luaufmt.toml", ".luaufmt.toml"];
+
+/// An error loading a config file.
+#[derive(Debug)]
+pub struct ConfigError {
+    /// The path of the config file.
+    pub path: PathBuf,
+
+    /// The actual error.
+    pub error: LoadConfigError,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+/// Finds and loads the config file that applies to each formatted file, which
+/// is the closest one found when going up from the file's folder. Results are
+/// cached so each folder is only searched once and each config file is only
+/// loaded once.
+#[derive(Debug, Default)]
+pub struct ConfigResolver {
+    /// A config file to use for all files instead of searching for one.
+    config_path: Option<PathBuf>,
+
+    /// The closest config file of each searched folder.
+    config_paths: Mutex<HashMap<PathBuf, Option<PathBuf>>>,
+
+    /// All loaded config files, by their path.
+    configs: Mutex<HashMap<PathBuf, ConfigFile>>,
+}
+
+impl ConfigResolver {
+    /// Create a new [`ConfigResolver`]. If `config_path` is passed, it'll be
+    /// used for all files.
+    #[inline]
+    pub fn new(config_path: Option<PathBuf>) -> Self {
+        Self {
+            config_path,
+            ..Default::default()
+        }
+    }
+
+    /// Finds the closest config file, starting from the passed folder.
+    fn find_in_folder(&self, folder: &Path) -> Option<PathBuf> {
+        if let Some(config_path) = self.config_paths.lock().unwrap().get(folder) {
+            return config_path.clone();
+        }
+
+        let config_path = CONFIG_FILE_NAMES
+            .iter()
+            .map(|name| folder.join(name))
+            .find(|path| path.is_file())
+            .or_else(|| folder.parent().and_then(|parent| self.find_in_folder(parent)));
+
+        self.config_paths
+            .lock()
+            .unwrap()
+            .insert(folder.to_path_buf(), config_path.clone());
+
+        config_path
+    }
+
+    /// Finds the config file that applies to the passed path, which can be a
+    /// file (that may not exist) or a folder.
+    pub fn find_config_path(&self, path: &Path) -> Option<PathBuf> {
+        if self.config_path.is_some() {
+            return self.config_path.clone();
+        }
+
+        let path = path::absolute(path).ok()?;
+
+        if path.is_dir() {
+            self.find_in_folder(&path)
+        } else {
+            self.find_in_folder(path.parent()?)
+        }
+    }
+
+    /// Gets the config that applies to the passed path, with the overrides
+    /// matching it applied. The default config is used if no config file is
+    /// found. Unknown keys are reported as warnings the first time a config
+    /// file is loaded.
+    pub fn config_for(&self, path: &Path) -> Result<Config, ConfigError> {
+        let Some(config_path) = self.find_config_path(path) else {
+            return Ok(Config::default());
+        };
+
+        if let Some(config_file) = self.configs.lock().unwrap().get(&config_path) {
+            return Ok(config_file.config_for(path));
+        }
+
+        let config_file =
+            ConfigFile::load(&config_path, FilesConfig::KEYS).map_err(|error| ConfigError {
+                path: config_path.clone(),
+                error,
+            })?;
+
+        for unknown_key in config_file.unknown_keys.iter() {
+            eprintln!(
+                "warning: {}:{}:{}: {unknown_key}",
+                unknown_key.path.display(),
+                unknown_key.line,
+                unknown_key.column,
+            );
+        }
+
+        let config = config_file.config_for(path);
+        self.configs.lock().unwrap().insert(config_path, config_file);
+
+        Ok(config)
+    }
+}
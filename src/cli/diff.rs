@@ -0,0 +1,100 @@
+//! Printing unified diffs between the original and the formatted code.
+
+use similar::{ChangeTag, TextDiff};
+use std::{
+    io::{self, IsTerminal},
+    path::Path,
+};
+
+/// Number of unchanged lines to show around each change.
+const CONTEXT_RADIUS: usize = 3;
+
+/// ANSI code for bold text, used for file headers.
+const BOLD: &str = "\x1b[1m";
+/// ANSI code for cyan text, used for hunk headers.
+const CYAN: &str = "\x1b[36m";
+/// ANSI code for red text, used for removed lines.
+const RED: &str = "\x1b[31m";
+/// ANSI code for green text, used for added lines.
+const GREEN: &str = "\x1b[32m";
+/// ANSI code resetting all styles.
+const RESET: &str = "\x1b[0m";
+
+/// When to colorize the diff.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Only colorize if stdout is a terminal.
+    #[default]
+    Auto,
+
+    /// Always colorize.
+    Always,
+
+    /// Never colorize.
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether or not the output should be colorized.
+    #[inline]
+    pub fn should_colorize(self) -> bool {
+        match self {
+            ColorChoice::Auto => io::stdout().is_terminal(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// Pushes `line` to `string`, wrapped in the passed ANSI `color` if `colorize`
+/// is `true`.
+fn push_line(string: &mut String, line: &str, color: &str, colorize: bool) {
+    if colorize {
+        string.push_str(color);
+        string.push_str(line.trim_end_matches(['\r', '\n']));
+        string.push_str(RESET);
+        string.push('\n');
+    } else {
+        string.push_str(line.trim_end_matches(['\r', '\n']));
+        string.push('\n');
+    }
+}
+
+/// Creates a unified diff between `original` and `formatted`, with file headers
+/// pointing to `path`. Returns an empty string if both are the same.
+pub fn unified_diff(path: &Path, original: &str, formatted: &str, colorize: bool) -> String {
+    let diff = TextDiff::from_lines(original, formatted);
+    let mut string = String::new();
+    let path = path.display();
+
+    for (i, hunk) in diff
+        .unified_diff()
+        .context_radius(CONTEXT_RADIUS)
+        .iter_hunks()
+        .enumerate()
+    {
+        if i == 0 {
+            push_line(&mut string, &format!("--- {path}"), BOLD, colorize);
+            push_line(&mut string, &format!("+++ {path}"), BOLD, colorize);
+        }
+
+        push_line(&mut string, &hunk.header().to_string(), CYAN, colorize);
+
+        for change in hunk.iter_changes() {
+            let (sign, color) = match change.tag() {
+                ChangeTag::Delete => ('-', RED),
+                ChangeTag::Insert => ('+', GREEN),
+                ChangeTag::Equal => (' ', ""),
+            };
+            let line = format!("{sign}{}", change.value());
+
+            push_line(&mut string, &line, color, colorize && !color.is_empty());
+
+            if change.missing_newline() {
+                string.push_str("\\ No newline at end of file\n");
+            }
+        }
+    }
+
+    string
+}
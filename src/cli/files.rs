@@ -0,0 +1,197 @@
+//! Deciding which files get formatted: ignore files, `.gitignore`s and the
+//! `include`/`exclude` globs.
+
+use ignore::{
+    Match, Walk, WalkBuilder,
+    gitignore::Gitignore,
+    overrides::{Override, OverrideBuilder},
+};
+use luau_fmt::LoadConfigError;
+use std::{
+    env, fs,
+    path::{self, Path},
+};
+
+/// Name of the ignore files specific to `luaufmt`. They use the same syntax as
+/// `.gitignore`.
+const LUAUFMT_IGNORE: &str = ".luaufmtignore";
+
+/// Name of git's ignore files.
+const GIT_IGNORE: &str = ".gitignore";
+
+/// The file-related keys of a `luaufmt.toml`.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, serde::Deserialize)]
+pub struct FilesConfig {
+    /// Only format files matching one of these globs. Everything is included if
+    /// it's empty.
+    #[serde(default)]
+    pub include: Vec<String>,
+
+    /// Never format files matching one of these globs.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// Whether or not to skip files ignored by `.gitignore`s.
+    #[serde(default = "default_respect_gitignore")]
+    pub respect_gitignore: bool,
+}
+
+/// Default value of [`FilesConfig::respect_gitignore`].
+#[inline]
+fn default_respect_gitignore() -> bool {
+    true
+}
+
+impl Default for FilesConfig {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            respect_gitignore: default_respect_gitignore(),
+        }
+    }
+}
+
+impl FilesConfig {
+    /// Names of all keys of a [`FilesConfig`].
+    pub const KEYS: &[&str] = &["include", "exclude", "respect_gitignore"];
+
+    /// Load the file-related keys from the `luaufmt.toml` at the passed path.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LoadConfigError> {
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+/// Decides which files get formatted.
+#[derive(Clone, Debug)]
+pub struct FileFilter {
+    /// The `include` and `exclude` globs of the config file, relative to its
+    /// folder, and the ones passed as arguments, relative to the current
+    /// directory.
+    globs: Vec<Override>,
+
+    /// Whether or not to skip files ignored by `.gitignore`s.
+    respect_gitignore: bool,
+}
+
+/// Compiles the passed `include` and `exclude` globs, relative to `root`.
+fn build_globs(
+    root: &Path,
+    include: &[String],
+    exclude: &[String],
+) -> Result<Override, ignore::Error> {
+    let mut builder = OverrideBuilder::new(root);
+
+    for glob in include.iter() {
+        builder.add(glob)?;
+    }
+    for glob in exclude.iter() {
+        builder.add(&format!("!{glob}"))?;
+    }
+
+    builder.build()
+}
+
+impl FileFilter {
+    /// Create a new [`FileFilter`] from the passed [`FilesConfig`], loaded from
+    /// the config file at `config_path`, and the `include` and `exclude` globs
+    /// passed as arguments.
+    pub fn new(
+        files_config: &FilesConfig,
+        config_path: Option<&Path>,
+        include: &[String],
+        exclude: &[String],
+    ) -> Result<Self, ignore::Error> {
+        let current_dir = env::current_dir()?;
+        let config_folder = match config_path {
+            Some(config_path) => {
+                let absolute_path = path::absolute(config_path)?;
+                absolute_path.parent().unwrap_or(&absolute_path).to_path_buf()
+            }
+            None => current_dir.clone(),
+        };
+
+        Ok(Self {
+            globs: vec![
+                build_globs(&config_folder, &files_config.include, &files_config.exclude)?,
+                build_globs(&current_dir, include, exclude)?,
+            ],
+            respect_gitignore: files_config.respect_gitignore,
+        })
+    }
+
+    /// Whether or not the `include` and `exclude` globs rule out the passed path.
+    fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        let Ok(path) = path::absolute(path) else {
+            return false;
+        };
+
+        self.globs
+            .iter()
+            .any(|globs| matches!(globs.matched(&path, is_dir), Match::Ignore(_)))
+    }
+
+    /// Walk the passed folder recursively, skipping ignored files and folders.
+    pub fn walk(&self, path: &Path) -> Walk {
+        let filter = self.clone();
+
+        WalkBuilder::new(path)
+            .hidden(false)
+            .ignore(false)
+            .git_ignore(self.respect_gitignore)
+            .git_global(self.respect_gitignore)
+            .git_exclude(self.respect_gitignore)
+            .require_git(false)
+            .add_custom_ignore_filename(LUAUFMT_IGNORE)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .filter_entry(move |entry| {
+                entry.file_name() != ".git"
+                    && !filter.is_excluded(
+                        entry.path(),
+                        entry.file_type().is_some_and(|file_type| file_type.is_dir()),
+                    )
+            })
+            .build()
+    }
+
+    /// Whether or not the passed file is ignored. The file doesn't need to
+    /// exist, which is useful for code read from stdin.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let Ok(path) = path::absolute(path) else {
+            return false;
+        };
+
+        if self.is_excluded(&path, false)
+            || self.globs.iter().any(|globs| {
+                path.ancestors()
+                    .skip(1)
+                    .take_while(|folder| folder.starts_with(globs.path()))
+                    .any(|folder| matches!(globs.matched(folder, true), Match::Ignore(_)))
+            })
+        {
+            return true;
+        }
+
+        let ignore_files: &[&str] = if self.respect_gitignore {
+            &[LUAUFMT_IGNORE, GIT_IGNORE]
+        } else {
+            &[LUAUFMT_IGNORE]
+        };
+
+        // Deeper ignore files take precedence, and `.luaufmtignore` takes
+        // precedence over `.gitignore` in the same folder.
+        for folder in path.ancestors().skip(1) {
+            for ignore_file in ignore_files {
+                let (gitignore, _) = Gitignore::new(folder.join(ignore_file));
+
+                match gitignore.matched_path_or_any_parents(&path, false) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => (),
+                }
+            }
+        }
+
+        false
+    }
+}
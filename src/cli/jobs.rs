@@ -0,0 +1,65 @@
+//! Running work on multiple threads.
+
+use std::{
+    num::NonZeroUsize,
+    panic,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+/// The default number of threads: one per available core.
+#[inline]
+pub fn default_jobs() -> NonZeroUsize {
+    thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
+
+/// Runs `job` on every item using a pool of `jobs` threads. The results are in
+/// the same order as the items, no matter which thread finishes first.
+pub fn run_parallel<T, R, F>(items: &[T], jobs: NonZeroUsize, job: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let jobs = jobs.get().min(items.len());
+    if jobs <= 1 {
+        return items.iter().map(job).collect();
+    }
+
+    let next_item = AtomicUsize::new(0);
+    let mut results = Vec::with_capacity(items.len());
+    results.resize_with(items.len(), || None);
+
+    thread::scope(|scope| {
+        let workers = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut finished = Vec::new();
+
+                    loop {
+                        let i = next_item.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(i) else {
+                            break finished;
+                        };
+
+                        finished.push((i, job(item)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for worker in workers {
+            // A panicking job is a bug, so we propagate it as is.
+            let finished = worker
+                .join()
+                .unwrap_or_else(|payload| panic::resume_unwind(payload));
+
+            for (i, result) in finished {
+                results[i] = Some(result);
+            }
+        }
+    });
+
+    // Every index was handed to exactly one worker, so all results are filled.
+    results.into_iter().map(Option::unwrap).collect()
+}
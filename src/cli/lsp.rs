@@ -0,0 +1,492 @@
+//! A language server, over stdio, for formatting documents in editors.
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+    DidChangeTextDocumentParams, DidChangeWatchedFilesRegistrationOptions,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, DidSaveTextDocumentParams,
+    DocumentFormattingParams, DocumentRangeFormattingParams, FileSystemWatcher, GlobPattern,
+    InitializeParams, InitializeResult, OneOf, Position, PositionEncodingKind, Range, Registration,
+    RegistrationParams, ServerCapabilities, ServerInfo, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextEdit, Uri,
+    notification::{
+        DidChangeTextDocument, DidChangeWatchedFiles, DidCloseTextDocument, DidOpenTextDocument,
+        DidSaveTextDocument, Notification as _,
+    },
+    request::{Formatting, RangeFormatting, RegisterCapability, Request as _},
+};
+use luau_fmt::{Config, FormattingError, compute_edits, format_range, format_with_config};
+use luau_parser::parser::Parser as LuauParser;
+use serde::de::DeserializeOwned;
+use std::{
+    collections::HashMap,
+    error::Error,
+    ops::Range as ByteRange,
+    path::{Path, PathBuf},
+};
+
+use super::config::{CONFIG_FILE_NAMES, ConfigResolver};
+
+/// Errors that stop the server.
+type ServerError = Box<dyn Error + Send + Sync>;
+
+/// How the characters of a [`Position`] are counted.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+enum Encoding {
+    /// In bytes.
+    Utf8,
+
+    /// In UTF-16 code units, the default of the protocol.
+    #[default]
+    Utf16,
+}
+
+impl Encoding {
+    /// The length of the passed character in this encoding.
+    #[inline]
+    fn len(self, character: char) -> usize {
+        match self {
+            Encoding::Utf8 => character.len_utf8(),
+            Encoding::Utf16 => character.len_utf16(),
+        }
+    }
+}
+
+/// Converts between byte offsets and [`Position`]s in a document.
+struct LineIndex<'a> {
+    /// The document.
+    content: &'a str,
+
+    /// The byte offset of the start of each line.
+    starts: Vec<usize>,
+
+    /// How characters are counted.
+    encoding: Encoding,
+}
+
+impl<'a> LineIndex<'a> {
+    /// Create a new [`LineIndex`] for the passed document.
+    fn new(content: &'a str, encoding: Encoding) -> Self {
+        let starts = [0]
+            .into_iter()
+            .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        Self {
+            content,
+            starts,
+            encoding,
+        }
+    }
+
+    /// The byte offset of the passed position. Positions past the end of their
+    /// line are at the end of it, before its line break.
+    fn offset_of(&self, position: Position) -> usize {
+        let Some(&start) = self.starts.get(position.line as usize) else {
+            return self.content.len();
+        };
+        let line = &self.content[start..];
+        let line = &line[..line.find('\n').unwrap_or(line.len())];
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        let mut units = 0;
+        for (i, character) in line.char_indices() {
+            if units >= position.character as usize {
+                return start + i;
+            }
+            units += self.encoding.len(character);
+        }
+
+        start + line.len()
+    }
+
+    /// The position of the passed byte offset.
+    fn position_of(&self, offset: usize) -> Position {
+        let line = self.starts.partition_point(|start| *start <= offset) - 1;
+        let character = self.content[self.starts[line]..offset]
+            .chars()
+            .map(|character| self.encoding.len(character))
+            .sum::<usize>();
+
+        Position::new(line as u32, character as u32)
+    }
+
+    /// The byte range of the passed range.
+    #[inline]
+    fn byte_range(&self, range: Range) -> ByteRange<usize> {
+        self.offset_of(range.start)..self.offset_of(range.end)
+    }
+}
+
+/// Gets the path of the passed `file://` URI.
+fn uri_to_path(uri: &Uri) -> Option<PathBuf> {
+    if uri.scheme().is_none_or(|scheme| scheme.as_str() != "file") {
+        return None;
+    }
+
+    let path = uri.path().as_estr().decode().into_string().ok()?;
+
+    // Windows paths look like `/C:/...` in URIs.
+    match path.strip_prefix('/') {
+        Some(windows_path) if windows_path.get(1..2) == Some(":") => {
+            Some(PathBuf::from(windows_path))
+        }
+        _ => Some(PathBuf::from(path.as_ref())),
+    }
+}
+
+/// Whether or not the passed path is one of a config file, or a file that may
+/// be extended by one.
+#[inline]
+fn is_config_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "toml")
+}
+
+/// The state of the server.
+struct Server<'a> {
+    /// The connection to the client.
+    connection: &'a Connection,
+
+    /// The content of all opened documents.
+    documents: HashMap<Uri, String>,
+
+    /// Finds the config of each document.
+    configs: ConfigResolver,
+
+    /// The first workspace folder, used to find the config of documents that
+    /// aren't files.
+    root: Option<PathBuf>,
+
+    /// How the characters of positions are counted.
+    encoding: Encoding,
+}
+
+impl Server<'_> {
+    /// Gets the config to format the passed document with.
+    fn config_for(&self, uri: &Uri) -> Result<Config, String> {
+        match uri_to_path(uri).or_else(|| self.root.clone()) {
+            Some(path) => self
+                .configs
+                .config_for(&path)
+                .map_err(|error| error.to_string()),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Drops all loaded configs, so they're loaded again the next time a
+    /// document is formatted.
+    #[inline]
+    fn reload_configs(&mut self) {
+        self.configs = ConfigResolver::default();
+    }
+
+    /// Formats the passed document, or only the passed range of it. Returns
+    /// [`None`] if it has syntax errors.
+    fn format(&self, uri: &Uri, range: Option<Range>) -> Result<Option<Vec<TextEdit>>, String> {
+        let Some(content) = self.documents.get(uri) else {
+            return Err(format!("unknown document `{}`", uri.as_str()));
+        };
+        let config = self.config_for(uri)?;
+        let line_index = LineIndex::new(content, self.encoding);
+
+        let cst = LuauParser::new(content).parse(uri.as_str());
+        let formatted_code = match range {
+            Some(range) => format_range(&cst, &config, line_index.byte_range(range)),
+            None => format_with_config(&cst, &config),
+        };
+        let formatted_code = match formatted_code {
+            Ok(formatted_code) => formatted_code,
+            Err(FormattingError::ErroneousCst) => return Ok(None),
+            Err(error) => return Err(error.to_string()),
+        };
+
+        Ok(Some(
+            compute_edits(content, &formatted_code)
+                .into_iter()
+                .map(|edit| TextEdit {
+                    range: Range::new(
+                        line_index.position_of(edit.range.start),
+                        line_index.position_of(edit.range.end),
+                    ),
+                    new_text: edit.new_text,
+                })
+                .collect(),
+        ))
+    }
+
+    /// Sends the passed response.
+    #[inline]
+    fn respond(&self, response: Response) -> Result<(), ServerError> {
+        self.connection.sender.send(Message::Response(response))?;
+
+        Ok(())
+    }
+
+    /// Responds to a formatting request.
+    fn respond_with_edits<P: DeserializeOwned>(
+        &self,
+        request: Request,
+        method: &str,
+        get_document: fn(&P) -> (&Uri, Option<Range>),
+    ) -> Result<(), ServerError> {
+        let request_id = request.id.clone();
+        let (id, params) = match request.extract::<P>(method) {
+            Ok(request) => request,
+            Err(error) => {
+                return self.respond(Response::new_err(
+                    request_id,
+                    ErrorCode::InvalidParams as i32,
+                    error.to_string(),
+                ));
+            }
+        };
+        let (uri, range) = get_document(&params);
+
+        self.respond(match self.format(uri, range) {
+            Ok(edits) => Response::new_ok(id, edits),
+            Err(message) => Response::new_err(id, ErrorCode::RequestFailed as i32, message),
+        })
+    }
+
+    /// Handles a request from the client.
+    fn handle_request(&self, request: Request) -> Result<(), ServerError> {
+        match request.method.as_str() {
+            Formatting::METHOD => self.respond_with_edits(
+                request,
+                Formatting::METHOD,
+                |params: &DocumentFormattingParams| (&params.text_document.uri, None),
+            ),
+            RangeFormatting::METHOD => self.respond_with_edits(
+                request,
+                RangeFormatting::METHOD,
+                |params: &DocumentRangeFormattingParams| {
+                    (&params.text_document.uri, Some(params.range))
+                },
+            ),
+            _ => self.respond(Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request `{}`", request.method),
+            )),
+        }
+    }
+
+    /// Handles a notification from the client.
+    fn handle_notification(&mut self, notification: Notification) {
+        let method = notification.method.clone();
+
+        match method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                if let Ok(params) = notification.extract::<DidOpenTextDocumentParams>(&method) {
+                    self.documents
+                        .insert(params.text_document.uri, params.text_document.text);
+                }
+            }
+            DidChangeTextDocument::METHOD => {
+                if let Ok(mut params) = notification.extract::<DidChangeTextDocumentParams>(&method)
+                    && let Some(change) = params.content_changes.pop()
+                {
+                    self.documents.insert(params.text_document.uri, change.text);
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                if let Ok(params) = notification.extract::<DidCloseTextDocumentParams>(&method) {
+                    self.documents.remove(&params.text_document.uri);
+                }
+            }
+            DidSaveTextDocument::METHOD => {
+                if let Ok(params) = notification.extract::<DidSaveTextDocumentParams>(&method)
+                    && uri_to_path(&params.text_document.uri)
+                        .is_some_and(|path| is_config_file(&path))
+                {
+                    self.reload_configs();
+                }
+            }
+            // Only config files are watched.
+            DidChangeWatchedFiles::METHOD => self.reload_configs(),
+            _ => (),
+        }
+    }
+
+    /// Asks the client to notify the server when config files change.
+    fn watch_config_files(&self) -> Result<(), ServerError> {
+        let watchers = CONFIG_FILE_NAMES
+            .iter()
+            .map(|name| FileSystemWatcher {
+                glob_pattern: GlobPattern::String(format!("**/{name}")),
+                kind: None,
+            })
+            .collect();
+        let params = RegistrationParams {
+            registrations: vec![Registration {
+                id: "luaufmt-config-files".to_string(),
+                method: DidChangeWatchedFiles::METHOD.to_string(),
+                register_options: Some(serde_json::to_value(
+                    DidChangeWatchedFilesRegistrationOptions { watchers },
+                )?),
+            }],
+        };
+
+        self.connection.sender.send(Message::Request(Request::new(
+            RequestId::from("luaufmt-watch-config-files".to_string()),
+            RegisterCapability::METHOD.to_string(),
+            params,
+        )))?;
+
+        Ok(())
+    }
+}
+
+/// Runs the language server over stdio until the client shuts it down.
+pub fn run() -> Result<(), ServerError> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let (id, params) = connection.initialize_start()?;
+    let params = serde_json::from_value::<InitializeParams>(params)?;
+
+    let encoding = if params
+        .capabilities
+        .general
+        .as_ref()
+        .and_then(|general| general.position_encodings.as_ref())
+        .is_some_and(|encodings| encodings.contains(&PositionEncodingKind::UTF8))
+    {
+        Encoding::Utf8
+    } else {
+        Encoding::Utf16
+    };
+    let can_watch_files = params
+        .capabilities
+        .workspace
+        .as_ref()
+        .and_then(|workspace| workspace.did_change_watched_files)
+        .and_then(|did_change_watched_files| did_change_watched_files.dynamic_registration)
+        .unwrap_or(false);
+    let root = params
+        .workspace_folders
+        .as_ref()
+        .and_then(|folders| folders.first())
+        .and_then(|folder| uri_to_path(&folder.uri));
+
+    let result = InitializeResult {
+        capabilities: ServerCapabilities {
+            position_encoding: Some(match encoding {
+                Encoding::Utf8 => PositionEncodingKind::UTF8,
+                Encoding::Utf16 => PositionEncodingKind::UTF16,
+            }),
+            text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+            document_formatting_provider: Some(OneOf::Left(true)),
+            document_range_formatting_provider: Some(OneOf::Left(true)),
+            ..Default::default()
+        },
+        server_info: Some(ServerInfo {
+            name: "luaufmt".to_string(),
+            version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        }),
+    };
+    connection.initialize_finish(id, serde_json::to_value(result)?)?;
+
+    let mut server = Server {
+        connection: &connection,
+        documents: HashMap::new(),
+        configs: ConfigResolver::default(),
+        root,
+        encoding,
+    };
+
+    if can_watch_files {
+        server.watch_config_files()?;
+    }
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+
+                server.handle_request(request)?;
+            }
+            Message::Notification(notification) => server.handle_notification(notification),
+            Message::Response(_) => (),
+        }
+    }
+
+    // The IO threads only stop once the connection is dropped.
+    drop(server);
+    drop(connection);
+    io_threads.join()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::Position;
+
+    use super::{Encoding, LineIndex};
+
+    /// Checks that the passed position and byte offset convert to each other.
+    fn assert_converts(line_index: &LineIndex, position: Position, offset: usize) {
+        assert_eq!(line_index.offset_of(position), offset, "{position:?}");
+        assert_eq!(line_index.position_of(offset), position, "{offset}");
+    }
+
+    #[test]
+    fn ascii() {
+        let line_index = LineIndex::new("local a\nprint(a)\n", Encoding::Utf16);
+
+        assert_converts(&line_index, Position::new(0, 0), 0);
+        assert_converts(&line_index, Position::new(0, 6), 6);
+        assert_converts(&line_index, Position::new(1, 0), 8);
+        assert_converts(&line_index, Position::new(1, 8), 16);
+        assert_converts(&line_index, Position::new(2, 0), 17);
+    }
+
+    #[test]
+    fn multi_byte_characters() {
+        // `é` is 2 bytes and 1 UTF-16 code unit.
+        let utf16 = LineIndex::new("a = \"é\"", Encoding::Utf16);
+        assert_converts(&utf16, Position::new(0, 5), 5);
+        assert_converts(&utf16, Position::new(0, 6), 7);
+
+        let utf8 = LineIndex::new("a = \"é\"", Encoding::Utf8);
+        assert_converts(&utf8, Position::new(0, 5), 5);
+        assert_converts(&utf8, Position::new(0, 7), 7);
+    }
+
+    #[test]
+    fn surrogate_pairs() {
+        // `😀` is 4 bytes and 2 UTF-16 code units.
+        let utf16 = LineIndex::new("a = \"😀\" b", Encoding::Utf16);
+        assert_converts(&utf16, Position::new(0, 5), 5);
+        assert_converts(&utf16, Position::new(0, 7), 9);
+        assert_converts(&utf16, Position::new(0, 9), 11);
+
+        // Positions in the middle of a character are after it.
+        assert_eq!(utf16.offset_of(Position::new(0, 6)), 9);
+
+        let utf8 = LineIndex::new("a = \"😀\" b", Encoding::Utf8);
+        assert_converts(&utf8, Position::new(0, 9), 9);
+    }
+
+    #[test]
+    fn positions_past_the_end() {
+        let line_index = LineIndex::new("ab\ncd", Encoding::Utf16);
+
+        assert_eq!(line_index.offset_of(Position::new(0, 10)), 2);
+        assert_eq!(line_index.offset_of(Position::new(1, 10)), 5);
+        assert_eq!(line_index.offset_of(Position::new(5, 0)), 5);
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let line_index = LineIndex::new("ab\r\ncd\r\n", Encoding::Utf16);
+
+        assert_converts(&line_index, Position::new(0, 2), 2);
+        assert_converts(&line_index, Position::new(1, 0), 4);
+        assert_converts(&line_index, Position::new(1, 2), 6);
+        assert_converts(&line_index, Position::new(2, 0), 8);
+        assert_eq!(line_index.offset_of(Position::new(0, 10)), 2);
+    }
+}
@@ -0,0 +1,8 @@
+//! Modules only used by the `luaufmt` binary.
+
+pub mod config;
+pub mod diff;
+pub mod files;
+pub mod jobs;
+pub mod lsp;
+pub mod range;
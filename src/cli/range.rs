@@ -0,0 +1,70 @@
+//! Parsing the part of a file to format from `--range` and `--lines`.
+
+use std::ops::{Range, RangeInclusive};
+
+/// The part of a file to format.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum FormatRange {
+    /// A range of bytes, with an exclusive end.
+    Bytes(Range<usize>),
+
+    /// A range of lines, starting from 1, with an inclusive end.
+    Lines(RangeInclusive<usize>),
+}
+
+impl FormatRange {
+    /// Parses a `START:END` byte range, as passed to `--range`.
+    pub fn parse_bytes(value: &str) -> Result<Self, String> {
+        let (start, end) = value
+            .split_once(':')
+            .ok_or_else(|| "expected `START:END`".to_string())?;
+        let start = start.trim().parse::<usize>().map_err(|error| error.to_string())?;
+        let end = end.trim().parse::<usize>().map_err(|error| error.to_string())?;
+
+        if start > end {
+            return Err(format!("the start ({start}) is after the end ({end})"));
+        }
+
+        Ok(Self::Bytes(start..end))
+    }
+
+    /// Parses a `FIRST-LAST` line range, as passed to `--lines`.
+    pub fn parse_lines(value: &str) -> Result<Self, String> {
+        let (first, last) = value
+            .split_once('-')
+            .ok_or_else(|| "expected `FIRST-LAST`".to_string())?;
+        let first = first.trim().parse::<usize>().map_err(|error| error.to_string())?;
+        let last = last.trim().parse::<usize>().map_err(|error| error.to_string())?;
+
+        if first == 0 {
+            return Err("lines start from 1".to_string());
+        }
+        if first > last {
+            return Err(format!("the first line ({first}) is after the last ({last})"));
+        }
+
+        Ok(Self::Lines(first..=last))
+    }
+
+    /// Gets the byte range this range covers in the passed code. Parts outside
+    /// the code are ignored.
+    pub fn to_byte_range(&self, content: &str) -> Range<usize> {
+        match self {
+            FormatRange::Bytes(range) => {
+                range.start.min(content.len())..range.end.min(content.len())
+            }
+            FormatRange::Lines(lines) => {
+                let mut line_starts = [0]
+                    .into_iter()
+                    .chain(content.match_indices('\n').map(|(i, _)| i + 1));
+
+                let start = line_starts.nth(lines.start() - 1).unwrap_or(content.len());
+                let end = line_starts
+                    .nth(lines.end() - lines.start())
+                    .map_or(content.len(), |next_start| next_start - 1);
+
+                start..end.max(start)
+            }
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Checking that formatted code still has every comment of the code it was
+//! formatted from.
+
+use luau_parser::{
+    prelude::{Lexer, Position, TokenType, Trivia},
+    types::Print,
+};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// A comment of the original code that isn't in the formatted code.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LostComment {
+    /// The comment, as it's written in the original code.
+    pub comment: String,
+
+    /// Where the comment starts in the original code.
+    pub position: Position,
+}
+
+impl Display for LostComment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "`{}` (at {}:{} in the original code) isn't in the formatted code",
+            self.comment,
+            self.position.line + 1,
+            self.position.character + 1,
+        )
+    }
+}
+
+/// A comment and where it starts.
+type LocatedComment = (String, Position);
+
+/// Moves the passed position to the end of `text`, which starts at it.
+fn advance(position: &mut Position, text: &str) {
+    for char in text.chars() {
+        if char == '\n' {
+            position.line += 1;
+            position.character = 0;
+        } else {
+            position.character += 1;
+        }
+    }
+}
+
+/// Collects the comments of the passed trivia, which starts at `position`.
+fn collect_trivia_comments(
+    trivia: &[Trivia],
+    mut position: Position,
+    comments: &mut Vec<LocatedComment>,
+) {
+    for trivia in trivia {
+        match trivia {
+            Trivia::Spaces(spaces) => advance(&mut position, spaces),
+            Trivia::Comment(comment) => {
+                let comment = comment.print();
+                let start = position;
+
+                advance(&mut position, &comment);
+                comments.push((comment, start));
+            }
+        }
+    }
+}
+
+/// Gets the comments of the passed code, in the order they're in it. They're
+/// found by lexing the code, which gives the same tokens as the ones of the
+/// [`Cst`](luau_parser::types::Cst) parsed from it, but is much faster than
+/// going through all of its nodes. The trailing trivia of every token is also
+/// the leading trivia of the next one, so only the leading trivia of the first
+/// token is used.
+fn comments_of(code: &str) -> Vec<LocatedComment> {
+    let mut lexer = Lexer::new(code);
+    let mut comments = Vec::new();
+
+    let mut token = lexer.next_token();
+    collect_trivia_comments(&token.leading_trivia, Position::default(), &mut comments);
+
+    loop {
+        let mut position = token.start;
+        advance(
+            &mut position,
+            &token.token_type.try_as_string().unwrap_or_default(),
+        );
+        collect_trivia_comments(&token.trailing_trivia, position, &mut comments);
+
+        if token.token_type == TokenType::EndOfFile {
+            break comments;
+        }
+
+        token = lexer.next_token();
+    }
+}
+
+/// Gets the text of the passed comment, without the `--` and the brackets
+/// around it, with its words separated by single spaces. Comments without
+/// words are their own text.
+fn text_of(comment: &str) -> String {
+    let text = &comment[2..];
+    let level = text
+        .strip_prefix('[')
+        .map(|rest| rest.len() - rest.trim_start_matches('=').len())
+        .filter(|&level| text[level + 1..].starts_with('['));
+
+    let text = match level {
+        Some(level) => {
+            let closing_bracket = "]".to_string() + &"=".repeat(level) + "]";
+            let text = &text[level + 2..];
+
+            text.strip_suffix(&closing_bracket).unwrap_or(text)
+        }
+        None => text,
+    };
+
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    if words.is_empty() {
+        comment.trim().to_string()
+    } else {
+        words.join(" ")
+    }
+}
+
+/// Takes the comment with the passed text out of `comments`, which are the
+/// texts of the formatted comments that weren't taken yet. Long single line
+/// comments are wrapped to multiple ones when formatting, so a run of
+/// consecutive comments that together have the passed text is also taken.
+fn take_comment(comments: &mut [Option<String>], text: &str) -> bool {
+    if let Some(comment) = comments
+        .iter_mut()
+        .find(|comment| comment.as_deref() == Some(text))
+    {
+        *comment = None;
+        return true;
+    }
+
+    for start in 0..comments.len() {
+        let mut joined = String::new();
+
+        for end in start..comments.len() {
+            let Some(part) = &comments[end] else {
+                break;
+            };
+
+            if !joined.is_empty() {
+                joined.push(' ');
+            }
+            joined.push_str(part);
+
+            if joined == text {
+                comments[start..=end].fill(None);
+                return true;
+            }
+            if !text.starts_with(&joined) {
+                break;
+            }
+        }
+    }
+
+    false
+}
+
+/// Finds the first comment of `original_code` that isn't in `formatted_code`,
+/// if any.
+pub(crate) fn find_lost_comment(original_code: &str, formatted_code: &str) -> Option<LostComment> {
+    let original_comments = comments_of(original_code);
+    if original_comments.is_empty() {
+        return None;
+    }
+
+    let mut formatted_comments = comments_of(formatted_code)
+        .into_iter()
+        .map(|(comment, _)| Some(text_of(&comment)))
+        .collect::<Vec<_>>();
+
+    original_comments
+        .into_iter()
+        .find(|(comment, _)| !take_comment(&mut formatted_comments, &text_of(comment)))
+        .map(|(comment, position)| LostComment { comment, position })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_lost_comment;
+
+    #[test]
+    fn wrapped_comments_are_kept() {
+        let original = "-- one two three four\nlocal x = 1\n";
+        let formatted = "-- one two\n-- three four\nlocal x = 1\n";
+
+        assert_eq!(find_lost_comment(original, formatted), None);
+    }
+
+    #[test]
+    fn removed_comments_are_reported() {
+        let original = "-- one two\nlocal x = 1 -- one\n-- two\n";
+        let formatted = "-- one two\nlocal x = 1\n";
+
+        let lost_comment = find_lost_comment(original, formatted).unwrap();
+        assert_eq!(lost_comment.comment, "-- one");
+        assert_eq!(lost_comment.position.line, 1);
+    }
+
+    #[test]
+    fn duplicate_comments_are_counted() {
+        let original = "-- todo\nlocal x = 1\n-- todo\n";
+        let formatted = "-- todo\nlocal x = 1\n";
+
+        let lost_comment = find_lost_comment(original, formatted).unwrap();
+        assert_eq!(lost_comment.position.line, 2);
+    }
+}
@@ -0,0 +1,598 @@
+//! Loading a [`Config`] from a `.toml` file.
+
+use ignore::{
+    Error as GlobError,
+    overrides::{Override, OverrideBuilder},
+};
+use std::{
+    error::Error,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    fs,
+    io::Error as IoError,
+    path::{self, Path, PathBuf},
+};
+use toml::de::{DeTable, DeValue, Error as TomlError};
+
+use super::{Config, PartialConfig};
+
+/// The key holding the path of the config file a config file extends.
+const EXTENDS_KEY: &str = "extends";
+
+/// The key holding the [`ConfigOverride`]s of a config file.
+const OVERRIDES_KEY: &str = "overrides";
+
+/// The key holding the globs of a [`ConfigOverride`].
+const FILES_KEY: &str = "files";
+
+/// Errors that may happen during loading of a [`Config`] from a `.toml` file.
+#[derive(Debug)]
+pub enum LoadConfigError {
+    /// An [`io error`](IoError).
+    Io(IoError),
+
+    /// An error parsing the TOML.
+    Toml(TomlError),
+
+    /// An invalid glob in the `files` of a [`ConfigOverride`].
+    Glob(GlobError),
+
+    /// An error loading a config file that's extended by this one.
+    Extends {
+        /// The path of the extended config file.
+        path: PathBuf,
+
+        /// The actual error.
+        error: Box<LoadConfigError>,
+    },
+
+    /// Config files extending each other in a cycle. Holds the paths of all
+    /// files in the cycle, starting and ending with the same one.
+    ExtendsCycle(Vec<PathBuf>),
+}
+
+impl From<IoError> for LoadConfigError {
+    #[inline]
+    fn from(value: IoError) -> Self {
+        Self::Io(value)
+    }
+}
+impl From<TomlError> for LoadConfigError {
+    #[inline]
+    fn from(value: TomlError) -> Self {
+        Self::Toml(value)
+    }
+}
+
+impl From<GlobError> for LoadConfigError {
+    #[inline]
+    fn from(value: GlobError) -> Self {
+        Self::Glob(value)
+    }
+}
+
+impl Display for LoadConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            // TOML errors already include the line and column they're at.
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Toml(error) => write!(f, "{}", error.to_string().trim_end()),
+            Self::Glob(error) => write!(f, "invalid glob in `{OVERRIDES_KEY}`: {error}"),
+            Self::Extends { path, error } => {
+                write!(f, "in extended config `{}`: {error}", path.display())
+            }
+            Self::ExtendsCycle(paths) => {
+                write!(f, "config files extend each other in a cycle: ")?;
+
+                for (i, path) in paths.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " -> ")?;
+                    }
+                    write!(f, "`{}`", path.display())?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for LoadConfigError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Toml(error) => Some(error),
+            Self::Glob(error) => Some(error),
+            Self::Extends { error, .. } => Some(error),
+            Self::ExtendsCycle(_) => None,
+        }
+    }
+}
+
+/// A key in a config file that isn't a known config.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnknownKey {
+    /// The path of the config file with this key, which is either the loaded
+    /// file or one it extends.
+    pub path: PathBuf,
+
+    /// The name of the key.
+    pub name: String,
+
+    /// The line the key is at, starting from 1.
+    pub line: usize,
+
+    /// The column the key is at, starting from 1.
+    pub column: usize,
+
+    /// The closest known key, if any is close enough to be a typo.
+    pub suggestion: Option<&'static str>,
+}
+
+impl Display for UnknownKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "unknown key `{}`", self.name)?;
+
+        if let Some(suggestion) = self.suggestion {
+            write!(f, ", did you mean `{suggestion}`?")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Number of single-character edits needed to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut previous_row = (0..=b.len()).collect::<Vec<usize>>();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current_row = vec![i + 1];
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_row[j] + usize::from(a_char != *b_char);
+            current_row.push(substitution.min(previous_row[j + 1] + 1).min(current_row[j] + 1));
+        }
+
+        previous_row = current_row;
+    }
+
+    previous_row[b.len()]
+}
+
+/// Finds the closest of `known_keys` to `key`, if it's close enough to be a typo.
+fn suggest_key(key: &str, known_keys: &[&'static str]) -> Option<&'static str> {
+    let max_distance = (key.len() / 3).max(1);
+
+    known_keys
+        .iter()
+        .map(|known_key| (edit_distance(key, known_key), *known_key))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, known_key)| known_key)
+}
+
+/// Gets the line and column (both starting from 1) of the passed byte offset.
+fn line_and_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Finds all keys of `table` that aren't in `known_keys`.
+fn find_unknown_keys_in(
+    path: &Path,
+    content: &str,
+    table: &DeTable,
+    known_keys: &[&'static str],
+    unknown_keys: &mut Vec<UnknownKey>,
+) {
+    for key in table.keys() {
+        if known_keys.iter().any(|known_key| *known_key == key.get_ref()) {
+            continue;
+        }
+
+        let (line, column) = line_and_column(content, key.span().start);
+
+        unknown_keys.push(UnknownKey {
+            path: path.to_path_buf(),
+            name: key.get_ref().to_string(),
+            line,
+            column,
+            suggestion: suggest_key(key.get_ref(), known_keys),
+        });
+    }
+}
+
+/// Finds all keys in the passed `.toml` content that aren't [`Config`] keys nor
+/// in `extra_keys`, including the ones in `[[overrides]]`.
+fn find_unknown_keys(
+    path: &Path,
+    content: &str,
+    extra_keys: &[&'static str],
+) -> Result<Vec<UnknownKey>, TomlError> {
+    let table = DeTable::parse(content)?;
+    let mut unknown_keys = Vec::new();
+
+    let known_keys = Config::KEYS
+        .iter()
+        .chain(extra_keys)
+        .chain(&[EXTENDS_KEY, OVERRIDES_KEY])
+        .copied()
+        .collect::<Vec<&'static str>>();
+    find_unknown_keys_in(path, content, table.get_ref(), &known_keys, &mut unknown_keys);
+
+    if let Some(DeValue::Array(overrides)) = table.get_ref().get(OVERRIDES_KEY).map(|v| v.get_ref())
+    {
+        let known_keys = Config::KEYS
+            .iter()
+            .chain(&[FILES_KEY])
+            .copied()
+            .collect::<Vec<&'static str>>();
+
+        for config_override in overrides {
+            if let DeValue::Table(config_override) = config_override.get_ref() {
+                find_unknown_keys_in(
+                    path,
+                    content,
+                    config_override,
+                    &known_keys,
+                    &mut unknown_keys,
+                );
+            }
+        }
+    }
+
+    unknown_keys.sort_by_key(|key| (key.line, key.column));
+
+    Ok(unknown_keys)
+}
+
+/// The `extends` key of a config file.
+#[derive(serde::Deserialize)]
+struct RawExtends {
+    /// The path of the extended config file, relative to the folder of the
+    /// extending one.
+    extends: Option<PathBuf>,
+}
+
+/// The globs of an `[[overrides]]` section of a config file.
+#[derive(serde::Deserialize)]
+struct RawOverrideFiles {
+    /// Globs of the files this override applies to.
+    files: Vec<String>,
+}
+
+/// All `[[overrides]]` sections of a config file, as written in the file. The
+/// globs and the keys are deserialized separately, as flattening them into one
+/// struct would lose the location of errors.
+#[derive(serde::Deserialize)]
+struct RawOverrides<T> {
+    /// The sections.
+    #[serde(default = "Vec::new")]
+    overrides: Vec<T>,
+}
+
+/// Keys of a config file that only apply to files matching some globs.
+#[derive(Clone)]
+pub struct ConfigOverride {
+    /// Globs of the files this override applies to. They're relative to the
+    /// folder of the config file and use the same syntax as `.gitignore`s.
+    pub files: Vec<String>,
+
+    /// The keys to change.
+    pub config: PartialConfig,
+
+    /// The compiled [`ConfigOverride::files`].
+    globs: Override,
+}
+
+impl Debug for ConfigOverride {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("ConfigOverride")
+            .field("files", &self.files)
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ConfigOverride {
+    /// Create a new [`ConfigOverride`] whose globs are relative to `root`.
+    pub fn new(root: &Path, files: Vec<String>, config: PartialConfig) -> Result<Self, GlobError> {
+        let mut builder = OverrideBuilder::new(root);
+
+        for glob in files.iter() {
+            builder.add(glob)?;
+        }
+
+        Ok(Self {
+            globs: builder.build()?,
+            files,
+            config,
+        })
+    }
+
+    /// Whether or not this override applies to the passed file.
+    #[inline]
+    pub fn matches(&self, path: &Path) -> bool {
+        self.globs.matched(path, false).is_whitelist()
+    }
+}
+
+/// The keys of a config file and of all the files it extends, merged.
+#[derive(Default)]
+struct Layers {
+    /// The merged config.
+    config: Config,
+
+    /// The globs and keys of all `[[overrides]]` sections, in the order
+    /// they're applied.
+    overrides: Vec<(Vec<String>, PartialConfig)>,
+
+    /// The paths of all extended config files, in the order they're applied.
+    extended: Vec<PathBuf>,
+
+    /// Unknown keys in all files.
+    unknown_keys: Vec<UnknownKey>,
+}
+
+impl Layers {
+    /// Load the config file at the passed path on top of the current layers,
+    /// after loading the file it extends, if any. `chain` holds the canonical
+    /// paths of the files currently being loaded, to detect cycles.
+    fn load(
+        &mut self,
+        path: &Path,
+        extra_keys: &[&'static str],
+        chain: &mut Vec<PathBuf>,
+    ) -> Result<(), LoadConfigError> {
+        let content = fs::read_to_string(path)?;
+        let canonical_path = fs::canonicalize(path)?;
+
+        if let Some(i) = chain.iter().position(|loaded| *loaded == canonical_path) {
+            let mut cycle = chain[i..].to_vec();
+            cycle.push(canonical_path);
+
+            return Err(LoadConfigError::ExtendsCycle(cycle));
+        }
+
+        if let Some(extends) = toml::from_str::<RawExtends>(&content)?.extends {
+            let extends = canonical_path
+                .parent()
+                .map_or_else(|| extends.clone(), |folder| folder.join(&extends));
+            let extends = fs::canonicalize(&extends).unwrap_or(extends);
+
+            chain.push(canonical_path);
+            self.load(&extends, extra_keys, chain)
+                .map_err(|error| match error {
+                    LoadConfigError::ExtendsCycle(_) => error,
+                    error => LoadConfigError::Extends {
+                        path: extends.clone(),
+                        error: Box::new(error),
+                    },
+                })?;
+            chain.pop();
+
+            self.extended.push(extends);
+        }
+
+        toml::from_str::<PartialConfig>(&content)?.apply(&mut self.config);
+
+        let files = toml::from_str::<RawOverrides<RawOverrideFiles>>(&content)?.overrides;
+        let configs = toml::from_str::<RawOverrides<PartialConfig>>(&content)?.overrides;
+        self.overrides.extend(
+            files
+                .into_iter()
+                .map(|raw| raw.files)
+                .zip(configs),
+        );
+
+        self.unknown_keys
+            .extend(find_unknown_keys(path, &content, extra_keys)?);
+
+        Ok(())
+    }
+}
+
+/// A [`Config`] loaded from a `.toml` file.
+#[derive(Clone, Debug)]
+pub struct ConfigFile {
+    /// The path of the file.
+    pub path: PathBuf,
+
+    /// The loaded config, merged with the ones it extends, without any
+    /// overrides.
+    pub config: Config,
+
+    /// The `[[overrides]]` sections of this file and the ones it extends, in
+    /// the order they're applied.
+    pub overrides: Vec<ConfigOverride>,
+
+    /// The paths of all config files this one extends, directly or not, with
+    /// the furthest one first.
+    pub extended: Vec<PathBuf>,
+
+    /// Keys in the file, and in the ones it extends, that aren't known
+    /// configs.
+    pub unknown_keys: Vec<UnknownKey>,
+}
+
+impl ConfigFile {
+    /// Load a [`ConfigFile`] from the passed path, after the files it extends.
+    /// `extra_keys` are keys that aren't [`Config`] keys but shouldn't be
+    /// reported as unknown, which is useful for tools storing their own
+    /// settings in the same file.
+    ///
+    /// Globs of overrides are relative to the folder of this file, even the
+    /// ones coming from extended files.
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        extra_keys: &[&'static str],
+    ) -> Result<Self, LoadConfigError> {
+        let path = path.as_ref();
+
+        let mut layers = Layers::default();
+        layers.load(path, extra_keys, &mut Vec::new())?;
+
+        let absolute_path = path::absolute(path)?;
+        let root = absolute_path.parent().unwrap_or(&absolute_path);
+
+        let overrides = layers
+            .overrides
+            .into_iter()
+            .map(|(files, config)| ConfigOverride::new(root, files, config))
+            .collect::<Result<Vec<ConfigOverride>, GlobError>>()?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            config: layers.config,
+            overrides,
+            extended: layers.extended,
+            unknown_keys: layers.unknown_keys,
+        })
+    }
+
+    /// Gets the config to format the passed file with, which is the base
+    /// config with all matching overrides applied on top of it.
+    pub fn config_for<P: AsRef<Path>>(&self, path: P) -> Config {
+        let mut config = self.config.clone();
+
+        let Ok(path) = path::absolute(path) else {
+            return config;
+        };
+
+        for config_override in self.overrides.iter() {
+            if config_override.matches(&path) {
+                config_override.config.apply(&mut config);
+            }
+        }
+
+        config
+    }
+}
+
+/// Load a [`Config`] from the passed path, merged with the config files it
+/// extends. The `[[overrides]]` sections aren't applied, use
+/// [`ConfigFile::config_for`] for them.
+#[inline]
+pub fn load_config<P: AsRef<Path>>(path: P) -> Result<Config, LoadConfigError> {
+    ConfigFile::load(path, &[]).map(|config_file| config_file.config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process};
+
+    use super::{ConfigFile, LoadConfigError};
+
+    /// Creates an empty folder for the passed test, with the passed files in it.
+    fn folder_with(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let folder = env::temp_dir().join(format!("luaufmt-{}-{test}", process::id()));
+        _ = fs::remove_dir_all(&folder);
+
+        for (name, content) in files {
+            let path = folder.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        folder
+    }
+
+    #[test]
+    fn unknown_keys_have_a_location_and_a_suggestion() {
+        let folder = folder_with(
+            "unknown-keys",
+            &[(
+                "luaufmt.toml",
+                "column_width = 80\ncolum_width = 100\n\n\
+                 [[overrides]]\nfiles = [\"*.lua\"]\nfoo = 1\n",
+            )],
+        );
+        let config_file = ConfigFile::load(folder.join("luaufmt.toml"), &[]).unwrap();
+
+        let keys = config_file
+            .unknown_keys
+            .iter()
+            .map(|key| (key.name.as_str(), key.line, key.column, key.suggestion))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            [("colum_width", 2, 1, Some("column_width")), ("foo", 6, 1, None)]
+        );
+        assert_eq!(
+            config_file.unknown_keys[0].to_string(),
+            "unknown key `colum_width`, did you mean `column_width`?"
+        );
+    }
+
+    #[test]
+    fn extra_keys_are_not_unknown() {
+        let folder = folder_with("extra-keys", &[("luaufmt.toml", "include = [\"src\"]\n")]);
+        let config_file = ConfigFile::load(folder.join("luaufmt.toml"), &["include"]).unwrap();
+
+        assert!(config_file.unknown_keys.is_empty());
+    }
+
+    #[test]
+    fn extends_applies_the_extending_file_on_top() {
+        let folder = folder_with(
+            "extends",
+            &[
+                ("base.toml", "column_width = 80\ntab_size = 2\n"),
+                ("project/luaufmt.toml", "extends = \"../base.toml\"\ncolumn_width = 120\n"),
+            ],
+        );
+        let config_file = ConfigFile::load(folder.join("project/luaufmt.toml"), &[]).unwrap();
+
+        assert_eq!(config_file.config.column_width, 120);
+        assert_eq!(config_file.config.tab_size, 2);
+        assert_eq!(
+            config_file.extended,
+            [fs::canonicalize(folder.join("base.toml")).unwrap()]
+        );
+    }
+
+    #[test]
+    fn extends_cycles_are_reported() {
+        let folder = folder_with(
+            "extends-cycle",
+            &[
+                ("a.toml", "extends = \"b.toml\"\n"),
+                ("b.toml", "extends = \"a.toml\"\n"),
+            ],
+        );
+
+        let Err(LoadConfigError::ExtendsCycle(cycle)) =
+            ConfigFile::load(folder.join("a.toml"), &[])
+        else {
+            panic!("the cycle wasn't detected");
+        };
+
+        let a = fs::canonicalize(folder.join("a.toml")).unwrap();
+        let b = fs::canonicalize(folder.join("b.toml")).unwrap();
+        assert_eq!(cycle, [a.clone(), b, a]);
+    }
+
+    #[test]
+    fn override_globs_are_relative_to_the_config_file() {
+        let folder = folder_with(
+            "overrides",
+            &[(
+                "project/luaufmt.toml",
+                "column_width = 80\n\n[[overrides]]\nfiles = [\"tests/**\"]\ncolumn_width = 120\n",
+            )],
+        );
+        let config_file = ConfigFile::load(folder.join("project/luaufmt.toml"), &[]).unwrap();
+
+        let column_width = |path: &str| config_file.config_for(folder.join(path)).column_width;
+        assert_eq!(column_width("project/tests/a.luau"), 120);
+        assert_eq!(column_width("project/tests/nested/a.luau"), 120);
+        assert_eq!(column_width("project/src/a.luau"), 80);
+        assert_eq!(column_width("tests/a.luau"), 80);
+    }
+}
@@ -18,95 +18,181 @@ reexport!(
     semicolon,
     naming_convention,
     function_parenthesis,
+    string_wrap,
+    target,
 );
 
+#[cfg(feature = "config-loading")]
+mod file;
+#[cfg(feature = "config-loading")]
+pub use file::*;
+
+/// A helper macro to define the config struct along with a partial version of
+/// it, where all keys are optional, and the names of its fields, which are the
+/// keys of a config file.
+macro_rules! define_config {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                pub $field:ident: $type:ty,
+            )*
+        }
+
+        $(#[$partial_meta:meta])*
+        pub struct $partial_name:ident;
+    ) => {
+        $(#[$meta])*
+        pub struct $name {
+            $(
+                $(#[$field_meta])*
+                pub $field: $type,
+            )*
+        }
+
+        impl $name {
+            /// Names of all keys that can be set in a config file.
+            pub const KEYS: &[&str] = &[$(stringify!($field)),*];
+        }
+
+        $(#[$partial_meta])*
+        pub struct $partial_name {
+            $(
+                $(#[$field_meta])*
+                pub $field: Option<$type>,
+            )*
+        }
+
+        impl $partial_name {
+            /// Overwrite the keys of the passed config with the ones set in
+            /// this one.
+            pub fn apply(&self, config: &mut $name) {
+                $(
+                    if let Some(value) = &self.$field {
+                        config.$field = value.clone();
+                    }
+                )*
+            }
+        }
+    };
+}
+
+define_config! {
 /// Struct representing the config file.
 #[rustfmt::skip]
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 #[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct Config {
-    /// The maximum width of characters per line. This configuration isn't very strict
-    /// as it doesn't actually limit the width of characters on the whole line but
-    /// rather parts of line. For example, an expression can never pass this width yet
-    /// a full local assignment can pass it. Also indentations aren't counted as
-    /// characters.
-    #[serde(default)]
+    /// The maximum width of a line, in columns, including its indentation. Lines
+    /// are broken where the code allows it, like between the items of a table or
+    /// the arguments of a call, until they fit in it, or can't be broken anymore.
+    /// Wide characters, like most emojis, take two columns, and tabs take
+    /// [`Config::tab_size`] ones.
     pub column_width: usize,
 
     /// The maximum width of a string per line. This fully overrides (and ignores)
     /// `column_width`.
-    #[serde(default)]
     pub string_width: usize,
 
+    /// How to wrap strings longer than [`Config::string_width`].
+    pub string_wrap: StringWrap,
+
     /// The maximum width of characters in a comment per line.
-    #[serde(default)]
     pub comments_width: usize,
 
     /// Quote style to use.
-    #[serde(default)]
     pub quote_style: QuoteStyle,
 
+    /// Whether or not to put spaces inside the braces of interpolated strings,
+    /// like `` `{ a + b }` ``. Expressions starting with a brace always get
+    /// them, as `{{` isn't allowed in interpolated strings.
+    pub interpolation_padding: bool,
+
     /// When to use compact table format (be one line).
-    #[serde(default)]
     pub compact_table: CompactTable,
 
     /// Whether to use spaces or tabs.
-    #[serde(default)]
     pub indent_style: IndentStyle,
 
     /// Number of spaces per tab (if [`Config::indent_style`] is
-    /// [`IndentStyle::Spaces`]).
-    #[serde(default)]
+    /// [`IndentStyle::Spaces`]), and the width of a tab when measuring lines.
     pub tab_size: IndentSize,
 
     /// Whether to use CRLF, LF, or CR line endings.
-    #[serde(default)]
     pub newline_style: NewLineStyle,
 
     /// When to have trailing commas in tables
-    #[serde(default)]
     pub trailing_commas: TrailingCommas,
 
+    /// Whether or not tables ending with a separator in the source code are
+    /// kept with a field per line, whatever [`Config::compact_table`] decides.
+    /// Single-line tables then never get a trailing comma, as it would expand
+    /// them the next time. Has no effect if [`Config::trailing_commas`] is
+    /// [`TrailingCommas::Never`], as the separator would be removed.
+    pub magic_trailing_comma: bool,
+
     /// Whether or not to keep the spacing between statements and not decrease it
     /// to a maximum of 2 lines (1 empty line).
-    #[serde(default)]
     pub keep_statements_spacing: bool,
 
     /// When to have `;` after statements.
-    #[serde(default)]
     pub semicolon: Semicolon,
 
     /// Whether or not to have a newline at the end of the file.
-    #[serde(default)]
     pub add_final_newline: bool,
 
-    /// Naming convention to use for variables.
-    #[serde(skip)]
-    #[serde(default)]
+    /// Naming convention to use for local variables, functions and parameters.
+    /// Only bindings declared in the formatted code are renamed, with all their
+    /// references, unless that would make an identifier refer to something
+    /// else, or they're named like a global, like `game` or `print`.
     pub variable_casing: NamingConvention,
 
-    /// Naming convention to use for methods.
+    /// Names of local variables, functions and parameters that are never
+    /// renamed by [`Config::variable_casing`], like `HTTPService`.
+    pub variable_casing_allow_list: Vec<String>,
+
+    /// Naming convention to use for methods. Unused, as methods are fields of
+    /// tables, which can be used from other files, so they're never renamed.
     #[serde(skip)]
-    #[serde(default)]
     pub method_casing: NamingConvention,
 
-    /// Naming convention to use for types.
-    #[serde(skip)]
-    #[serde(default)]
+    /// Naming convention to use for local types, type functions and generic
+    /// type parameters, renamed like [`Config::variable_casing`]. Exported types
+    /// are never renamed, as other files can use them.
     pub type_casing: NamingConvention,
 
+    /// Names of local types and generic type parameters that are never renamed
+    /// by [`Config::type_casing`].
+    pub type_casing_allow_list: Vec<String>,
+
     /// Whether or not to sort `require(...)` that are in the same block.
-    #[serde(default)]
     pub sort_requires: bool,
 
     /// Whether or not to sort `game:GetService(...)` and `game.<IDENT>` that
     /// are in the same block.
-    #[serde(default)]
     pub sort_services: bool,
 
     /// When to include parenthesis around function arguments.
-    #[serde(default)]
     pub function_parenthesis: FunctionParenthesis,
+
+    /// Whether or not to parse the formatted code again and check that it
+    /// means the same as the original code, failing instead of returning it if
+    /// it doesn't.
+    pub verify: bool,
+
+    /// The dialect the code is written in.
+    pub target: Target,
+}
+
+/// A [`Config`] where all keys are optional, used to only change some keys of
+/// another config.
+#[rustfmt::skip]
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct PartialConfig;
 }
 
 impl Default for Config {
@@ -114,9 +200,11 @@ impl Default for Config {
         Self {
             column_width: 100,
             string_width: 60,
+            string_wrap: Default::default(),
             comments_width: 80,
 
             quote_style: Default::default(),
+            interpolation_padding: false,
 
             compact_table: Default::default(),
 
@@ -126,6 +214,7 @@ impl Default for Config {
             newline_style: Default::default(),
 
             trailing_commas: Default::default(),
+            magic_trailing_comma: false,
 
             keep_statements_spacing: false,
             semicolon: Default::default(),
@@ -133,13 +222,40 @@ impl Default for Config {
             add_final_newline: true,
 
             variable_casing: NamingConvention::None,
+            variable_casing_allow_list: Vec::new(),
             method_casing: NamingConvention::None,
             type_casing: NamingConvention::None,
+            type_casing_allow_list: Vec::new(),
 
             sort_services: true,
             sort_requires: true,
 
             function_parenthesis: Default::default(),
+
+            verify: false,
+
+            target: Default::default(),
+        }
+    }
+}
+
+impl Config {
+    /// This config without naming conventions, to format only some of the
+    /// code, as renaming bindings could miss their references in the rest.
+    pub(crate) fn without_renaming(&self) -> Self {
+        Self {
+            variable_casing: NamingConvention::None,
+            type_casing: NamingConvention::None,
+            ..self.clone()
+        }
+    }
+
+    /// How to wrap strings longer than [`Config::string_width`], which is never
+    /// with `\z` escapes for [`Target::Lua51`], as it doesn't support them.
+    pub(crate) fn string_wrap(&self) -> StringWrap {
+        match (self.string_wrap, self.target) {
+            (StringWrap::Escape, Target::Lua51) => StringWrap::Concatenation,
+            (string_wrap, _) => string_wrap,
         }
     }
 }
@@ -1,5 +1,7 @@
 //! [`NamingConvention`] enum.
 
+use std::mem;
+
 /// Different naming conventions
 #[rustfmt::skip]
 #[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
@@ -17,76 +19,143 @@ pub enum NamingConvention {
     #[serde(rename = "snake_case")]
     Snake,
 
+    /// `SCREAMING_SNAKE_CASE`, usually for constants.
+    #[serde(rename = "SCREAMING_SNAKE_CASE")]
+    ScreamingSnake,
+
     /// Keep it as it is.
     #[default]
     #[serde(rename = "none")]
     None,
 }
 
-#[inline]
-fn is_splitter(char: char) -> bool {
-    char == '_' || char.is_uppercase()
+/// Whether or not the character at `i` starts a new word: it's uppercase, and
+/// either ends a word in lowercase or digits, like `B` in `fooBar`, or ends an
+/// acronym while starting a word in lowercase, like `S` in `HTTPService`.
+fn starts_word(chars: &[char], i: usize) -> bool {
+    let Some(previous) = i.checked_sub(1).map(|previous| chars[previous]) else {
+        return false;
+    };
+
+    let is_next_lowercase = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+
+    chars[i].is_uppercase()
+        && (previous.is_lowercase()
+            || previous.is_ascii_digit()
+            || (previous.is_uppercase() && is_next_lowercase))
 }
 
-/// Splits the passed identifier into words
+/// Splits the passed identifier into words, without the underscores between
+/// them. Acronyms are single words, like `HTTP` in `HTTPService`.
 fn get_words(identifier: &str) -> Vec<String> {
+    let chars = identifier.chars().collect::<Vec<_>>();
     let mut words = Vec::new();
     let mut current_word = String::new();
 
-    for (i, char) in identifier.chars().enumerate() {
-        if i != 0 && is_splitter(char) {
-            words.push(current_word);
-            current_word = String::new();
+    for (i, char) in chars.iter().enumerate() {
+        if (*char == '_' || starts_word(&chars, i)) && !current_word.is_empty() {
+            words.push(mem::take(&mut current_word));
         }
 
-        current_word.push(char);
+        if *char != '_' {
+            current_word.push(*char);
+        }
     }
 
-    words.push(current_word);
+    if !current_word.is_empty() {
+        words.push(current_word);
+    }
     words
 }
 
-/// Capitalizes the first letter if the passed word if it's at least one
-/// character long
-fn capitalize_first_letter(word: &mut str) -> &str {
-    if word.len() > 1 {
-        word[..1].make_ascii_uppercase();
-    }
+/// Capitalizes the first letter of the passed word, and lowercases the others
+/// if `is_shouting`, as the word then comes from an identifier in uppercase.
+fn capitalize_first_letter(word: &str, is_shouting: bool) -> String {
+    let mut chars = word.chars();
+    let Some(first) = chars.next() else {
+        return String::new();
+    };
 
-    word
+    let rest = chars.as_str();
+    if is_shouting {
+        first.to_uppercase().chain(rest.to_lowercase().chars()).collect()
+    } else {
+        first.to_uppercase().chain(rest.chars()).collect()
+    }
 }
 
 impl NamingConvention {
-    /// Applies self onto the passed identifier and returns the new one.
+    /// Applies self onto the passed identifier and returns the new one. The
+    /// underscores it starts and ends with, like in `_cache`, are kept.
     pub fn apply(&self, identifier: &str) -> String {
-        if *self == Self::None {
+        let name = identifier.trim_matches('_');
+        if *self == Self::None || name.is_empty() {
             return identifier.to_string()
         }
 
-        let mut words = get_words(identifier);
+        let prefix = &identifier[..identifier.len() - identifier.trim_start_matches('_').len()];
+        let suffix = &identifier[identifier.trim_end_matches('_').len()..];
+        let is_shouting = !name.chars().any(char::is_lowercase);
+        let words = get_words(name);
 
-        match self {
+        let name = match self {
             NamingConvention::Camel => words
-                        .iter_mut()
+                        .iter()
                         .enumerate()
                         .map(|(i, word)| {
                             if i == 0 {
                                 word.to_lowercase()
                             } else {
-                                capitalize_first_letter(word).to_string()
+                                capitalize_first_letter(word, is_shouting)
                             }
                         })
                         .collect::<String>(),
             NamingConvention::Pascal => words
-                        .iter_mut()
-                        .map(|word| capitalize_first_letter(word))
+                        .iter()
+                        .map(|word| capitalize_first_letter(word, is_shouting))
                         .collect::<String>(),
             NamingConvention::Snake => words
                         .iter()
                         .map(|word| word.to_lowercase())
                         .collect::<Vec<String>>()
                         .join("_"),
+            NamingConvention::ScreamingSnake => words
+                        .iter()
+                        .map(|word| word.to_uppercase())
+                        .collect::<Vec<String>>()
+                        .join("_"),
             NamingConvention::None => unreachable!(),
-                        }
+        };
+
+        format!("{prefix}{name}{suffix}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NamingConvention;
+
+    /// Checks how identifiers are split into words and renamed.
+    #[test]
+    fn naming_conventions_apply() {
+        let cases = [
+            (NamingConvention::Snake, "HTTPService", "http_service"),
+            (NamingConvention::Snake, "getHTTPResponse2", "get_http_response2"),
+            (NamingConvention::Camel, "HTTPService", "httpService"),
+            (NamingConvention::Pascal, "MAX_SIZE", "MaxSize"),
+            (NamingConvention::ScreamingSnake, "maxSize", "MAX_SIZE"),
+            (NamingConvention::Snake, "_privateValue", "_private_value"),
+            (NamingConvention::Camel, "__index", "__index"),
+            (NamingConvention::Camel, "value_", "value_"),
+            (NamingConvention::None, "some_Name", "some_Name"),
+        ];
+
+        for (naming_convention, identifier, expected) in cases {
+            assert_eq!(
+                naming_convention.apply(identifier),
+                expected,
+                "{naming_convention:?}"
+            );
+        }
     }
 }
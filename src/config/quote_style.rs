@@ -28,24 +28,40 @@ pub enum QuoteStyle {
     PreferDouble,
 }
 
-/// Escaped `'`
-const ESCAPED_QUOTE: &str = r"\'";
 /// `'`
-const QUOTE: &str = r"'";
+const QUOTE: char = '\'';
 
-/// Escaped `"`
-const ESCAPED_DOUBLE_QUOTE: &str = r#"\""#;
 /// `"`
-const DOUBLE_QUOTE: &str = r#"""#;
-
-/// Does conversion between quotation types.
-macro_rules! convert {
-    ($str: ident, $quotes: ident, $(($from:ident => $to:ident)),* $(,)?) => {{
-        let mut string = $str $(.replace($from, $to))*;
-        string.replace_range(..1, $quotes);
-        string.replace_range(string.len() - 1.., $quotes);
-        string
-    }};
+const DOUBLE_QUOTE: char = '"';
+
+/// Converts a string quoted with `from` to one quoted with `to`. Escaped `from`
+/// quotes don't need escaping anymore, while `to` quotes now do. Other escape
+/// sequences, like `\\`, are kept as they are.
+fn convert_quotes(string: &str, from: char, to: char) -> String {
+    let mut converted = String::with_capacity(string.len() + 2);
+    let mut chars = string[1..string.len() - 1].chars();
+
+    converted.push(to);
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => match chars.next() {
+                Some(escaped) if escaped == from => converted.push(from),
+                Some(escaped) => {
+                    converted.push('\\');
+                    converted.push(escaped);
+                }
+                None => converted.push('\\'),
+            },
+            _ if char == to => {
+                converted.push('\\');
+                converted.push(to);
+            }
+            _ => converted.push(char),
+        }
+    }
+    converted.push(to);
+
+    converted
 }
 
 /// Counts the number of escapes in the passed string
@@ -79,21 +95,15 @@ impl QuoteStyle {
         match self {
             QuoteStyle::Single => match luau_string {
                 LuauString::SingleQuotes(smol_str) => smol_str.to_string(),
-                LuauString::DoubleQuotes(smol_str) => convert!(
-                    smol_str,
-                    QUOTE,
-                    (ESCAPED_DOUBLE_QUOTE => DOUBLE_QUOTE),
-                    (QUOTE => ESCAPED_QUOTE)
-                ),
+                LuauString::DoubleQuotes(smol_str) => {
+                    convert_quotes(smol_str, DOUBLE_QUOTE, QUOTE)
+                }
                 _ => unreachable!(),
             },
             QuoteStyle::Double => match luau_string {
-                LuauString::SingleQuotes(smol_str) => convert!(
-                    smol_str,
-                    DOUBLE_QUOTE,
-                    (ESCAPED_QUOTE => QUOTE),
-                    (DOUBLE_QUOTE => ESCAPED_DOUBLE_QUOTE)
-                ),
+                LuauString::SingleQuotes(smol_str) => {
+                    convert_quotes(smol_str, QUOTE, DOUBLE_QUOTE)
+                }
                 LuauString::DoubleQuotes(smol_str) => smol_str.to_string(),
                 _ => unreachable!(),
             },
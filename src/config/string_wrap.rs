@@ -0,0 +1,20 @@
+/// How to wrap strings longer than [`Config::string_width`](super::Config::string_width).
+#[rustfmt::skip]
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StringWrap {
+    /// End each line but the last with a `\z` escape, which skips the line
+    /// break and the indentation after it.
+    #[default]
+    Escape,
+
+    /// Split the string into several ones joined with `..`. Strings that can't
+    /// be expressions, like function arguments without parentheses or string
+    /// types, aren't wrapped. Strings joined with `..` on different lines are
+    /// kept on them, so they aren't joined back.
+    Concatenation,
+
+    /// Never wrap strings.
+    Never,
+}
@@ -0,0 +1,16 @@
+//! [`Target`] enum.
+
+/// The dialect formatted code is written in.
+#[rustfmt::skip]
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    /// Luau, which supports all the syntax the formatter can write.
+    #[default]
+    Luau,
+
+    /// Lua 5.1, which doesn't support `\z` escapes, so long strings are split
+    /// with `..` instead.
+    Lua51,
+}
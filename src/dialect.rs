@@ -0,0 +1,146 @@
+//! Finding the syntax of the code that the [`Target`] of the config doesn't
+//! support, which is reported as warnings, as it's kept as it is.
+
+use luau_parser::{prelude::Position, types::Cst};
+use serde_json::Value;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::{
+    config::{Config, Target},
+    verify::{serialize, start_of, variant_of},
+};
+
+/// Syntax of the original code that the [`Target`] of the config doesn't
+/// support.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnsupportedSyntax {
+    /// What the syntax is, like `type annotations`.
+    pub syntax: &'static str,
+
+    /// Where its first use starts in the original code.
+    pub position: Option<Position>,
+}
+
+impl Display for UnsupportedSyntax {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{} aren't supported by Lua 5.1", self.syntax)?;
+
+        match self.position {
+            Some(position) => write!(
+                f,
+                " (first used at {}:{})",
+                position.line + 1,
+                position.character + 1
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The Luau-only syntax of the passed serialized token, if any.
+fn token_syntax(token_type: &Value) -> Option<&'static str> {
+    if token_type.pointer("/Operator").and_then(Value::as_str) == Some("FloorDivision") {
+        return Some("floor divisions");
+    }
+
+    if let Some(number) = token_type.pointer("/Literal/Number") {
+        return match variant_of(number) {
+            Some("Binary") => Some("binary numbers"),
+            _ if number.to_string().contains('_') => Some("digit separators"),
+            _ => None,
+        };
+    }
+
+    let string = token_type.pointer("/Literal/String")?;
+    match variant_of(string)? {
+        "Backticks" => Some("interpolated strings"),
+        "SingleQuotes" | "DoubleQuotes" => {
+            let mut chars = string.as_object()?.values().next()?.as_str()?.chars();
+
+            while let Some(char) = chars.next() {
+                if char != '\\' {
+                    continue;
+                }
+
+                match chars.next() {
+                    Some('z') => return Some("`\\z` escapes"),
+                    Some('x') => return Some("`\\x` escapes"),
+                    Some('u') => return Some("`\\u` escapes"),
+                    _ => (),
+                }
+            }
+
+            None
+        }
+        _ => None,
+    }
+}
+
+/// The Luau-only syntax of the passed field of a serialized node, if any.
+fn field_syntax(key: &str, value: &Value) -> Option<&'static str> {
+    match (key, value) {
+        (_, Value::Null) => None,
+        ("type" | "return_type", _) => Some("type annotations"),
+        ("generics", _) => Some("generics"),
+        ("attributes", Value::Array(attributes)) if !attributes.is_empty() => Some("attributes"),
+        ("TypeDefinition", _) => Some("type definitions"),
+        ("TypeFunction", _) => Some("type functions"),
+        ("CompoundSetExpression", _) => Some("compound assignments"),
+        ("IfExpression", _) => Some("if expressions"),
+        ("TypeCast", _) => Some("type casts"),
+        ("Continue", _) => Some("`continue` statements"),
+        _ => None,
+    }
+}
+
+/// Adds the passed syntax, used at the passed node, to `found`, unless it was
+/// already found.
+fn add_syntax(syntax: &'static str, node: &Value, found: &mut Vec<UnsupportedSyntax>) {
+    if found.iter().all(|unsupported| unsupported.syntax != syntax) {
+        found.push(UnsupportedSyntax {
+            syntax,
+            position: start_of(node),
+        });
+    }
+}
+
+/// Finds the Luau-only syntax of the passed serialized node and its children.
+fn find_luau_syntax(value: &Value, found: &mut Vec<UnsupportedSyntax>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(syntax) = map.get("token_type").and_then(token_syntax) {
+                add_syntax(syntax, value, found);
+            }
+
+            for (key, child) in map {
+                if let Some(syntax) = field_syntax(key, child) {
+                    add_syntax(syntax, child, found);
+                }
+
+                find_luau_syntax(child, found);
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                find_luau_syntax(value, found);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Finds the syntax of the passed [`Cst`] that [`Config::target`] doesn't
+/// support, which the formatter keeps as it is. Each kind of syntax is only
+/// reported once, where it's first used. Nothing is reported for Luau, which
+/// supports everything.
+pub fn unsupported_syntax(cst: &Cst, config: &Config) -> Vec<UnsupportedSyntax> {
+    if config.target != Target::Lua51 {
+        return Vec::new();
+    }
+
+    let block = serialize(&cst.block);
+    let mut found = Vec::new();
+    find_luau_syntax(&block, &mut found);
+
+    found
+}
@@ -0,0 +1,488 @@
+//! The document the formatter builds from the [`Cst`](luau_parser::types::Cst),
+//! and the printer laying it out. The formatter only describes where lines may
+//! break, with groups, and the printer decides, once per group, whether it fits
+//! on the current line or has to break.
+
+use unicode_width::UnicodeWidthChar;
+
+use crate::{config::Config, traits::Indentation};
+
+/// A document describing formatted code.
+#[derive(Clone, Debug, Default)]
+pub(crate) enum Doc {
+    /// Nothing.
+    #[default]
+    Nil,
+
+    /// Text printed as-is. It may have line breaks, like multi-line strings,
+    /// in which case the lines after the first one are kept as they are.
+    Text(String),
+
+    /// A comment on the same line as the code around it, printed as-is. It isn't
+    /// counted when checking if a group fits, as it's moved to the end of the
+    /// line if the line breaks after it, which mustn't change the layout when
+    /// formatting the code again.
+    Comment(String),
+
+    /// A space, unless the line already ends with whitespace.
+    Space,
+
+    /// A space, or a line break if the enclosing group breaks.
+    Line,
+
+    /// Nothing, or a line break if the enclosing group breaks.
+    SoftLine,
+
+    /// A line break, whether the enclosing group breaks or not. It doesn't force
+    /// the group to break, so a group ending the line before it, like the
+    /// arguments of a call ending with a function, can stay flat.
+    HardLine,
+
+    /// A line break in a token, like a string split with `\z`, which line
+    /// suffixes can't be put before.
+    TokenLine,
+
+    /// A line break, unless the line only has its indentation. Put before
+    /// comments that must start their own lines.
+    LineStart,
+
+    /// The passed documents, one after the other.
+    Concat(Vec<Doc>),
+
+    /// The passed document, with its line breaks indented once more.
+    Indent(Box<Doc>),
+
+    /// The passed document, with its line breaks indented once more if the
+    /// enclosing group breaks.
+    IndentIfBreak(Box<Doc>),
+
+    /// A group whose line breaks are all printed, or none of them. It breaks if
+    /// it doesn't fit on the current line, or if `breaks` is set.
+    Group {
+        /// The contents of the group.
+        contents: Box<Doc>,
+
+        /// Whether or not the group always breaks.
+        breaks: bool,
+    },
+
+    /// One of the passed documents, depending on whether the enclosing group
+    /// breaks or not.
+    IfBreak {
+        /// The document printed if the group breaks.
+        broken: Box<Doc>,
+
+        /// The document printed if the group doesn't break.
+        flat: Box<Doc>,
+    },
+
+    /// The passed document, printed before the next line break instead of
+    /// where it is, like a single-line comment at the end of a line.
+    LineSuffix(Box<Doc>),
+
+    /// Forces the enclosing group to break if it's after one of its line
+    /// breaks, and the groups around it to break if it would be on their first
+    /// line. Put before comments that must be on their own lines.
+    BreakParent,
+}
+
+impl Doc {
+    /// Creates a new [`Doc::Text`].
+    #[inline]
+    pub(crate) fn text(text: impl Into<String>) -> Self {
+        Self::Text(text.into())
+    }
+
+    /// Creates a new [`Doc::Indent`].
+    #[inline]
+    pub(crate) fn indent(doc: Doc) -> Self {
+        Self::Indent(Box::new(doc))
+    }
+
+    /// Creates a new [`Doc::IndentIfBreak`].
+    #[inline]
+    pub(crate) fn indent_if_break(doc: Doc) -> Self {
+        Self::IndentIfBreak(Box::new(doc))
+    }
+
+    /// Creates a new [`Doc::Group`], which breaks if it has a [`Doc::BreakParent`]
+    /// after one of its line breaks, before any [`Doc::HardLine`].
+    #[inline]
+    pub(crate) fn group(contents: Doc) -> Self {
+        Self::group_with(contents, false)
+    }
+
+    /// Creates a new [`Doc::Group`], like [`Doc::group`], that always breaks if
+    /// `breaks` is set.
+    pub(crate) fn group_with(contents: Doc, breaks: bool) -> Self {
+        let breaks = breaks || find_break_parent(&contents, &mut false).unwrap_or_default();
+
+        Self::Group {
+            contents: Box::new(contents),
+            breaks,
+        }
+    }
+
+    /// Creates a new [`Doc::IfBreak`].
+    #[inline]
+    pub(crate) fn if_break(broken: Doc, flat: Doc) -> Self {
+        Self::IfBreak {
+            broken: Box::new(broken),
+            flat: Box::new(flat),
+        }
+    }
+
+    /// Creates a new [`Doc::LineSuffix`].
+    #[inline]
+    pub(crate) fn line_suffix(doc: Doc) -> Self {
+        Self::LineSuffix(Box::new(doc))
+    }
+
+    /// Removes the [`Doc::Space`] this document ends with, if any, like the one
+    /// after a comment followed by punctuation.
+    pub(crate) fn trim_trailing_space(&mut self) {
+        match self {
+            Self::Space => *self = Self::Nil,
+            Self::Concat(docs) => {
+                if let Some(doc) = docs.iter_mut().rev().find(|doc| !doc.is_empty()) {
+                    doc.trim_trailing_space();
+                }
+            }
+            Self::Indent(doc) | Self::IndentIfBreak(doc) => doc.trim_trailing_space(),
+            Self::Group { contents, .. } => contents.trim_trailing_space(),
+            _ => {}
+        }
+    }
+
+    /// Whether or not this document has a group that always breaks.
+    pub(crate) fn has_broken_group(&self) -> bool {
+        match self {
+            Self::Concat(docs) => docs.iter().any(Self::has_broken_group),
+            Self::Indent(doc) | Self::IndentIfBreak(doc) => doc.has_broken_group(),
+            Self::Group { contents, breaks } => *breaks || contents.has_broken_group(),
+            _ => false,
+        }
+    }
+
+    /// Whether or not this document prints nothing.
+    pub(crate) fn is_empty(&self) -> bool {
+        match self {
+            Self::Nil | Self::BreakParent => true,
+            Self::Text(text) | Self::Comment(text) => text.is_empty(),
+            Self::Concat(docs) => docs.iter().all(Self::is_empty),
+            Self::Indent(doc) | Self::IndentIfBreak(doc) => doc.is_empty(),
+            Self::Group { contents, .. } => contents.is_empty(),
+            _ => false,
+        }
+    }
+}
+
+impl From<Vec<Doc>> for Doc {
+    #[inline]
+    fn from(docs: Vec<Doc>) -> Self {
+        Self::Concat(docs)
+    }
+}
+
+/// Searches the passed contents of a group for a [`Doc::BreakParent`] after one
+/// of its line breaks, `has_line` being whether one was already found. Returns
+/// whether one was found, or [`None`] if the search must go on. Nested groups
+/// and line suffixes break on their own, and nothing after a [`Doc::HardLine`]
+/// is on the lines of the group that can break, like the body of a function.
+fn find_break_parent(doc: &Doc, has_line: &mut bool) -> Option<bool> {
+    match doc {
+        Doc::Line | Doc::SoftLine => {
+            *has_line = true;
+            None
+        }
+        Doc::BreakParent if *has_line => Some(true),
+        Doc::HardLine => Some(false),
+        Doc::Concat(docs) => docs.iter().find_map(|doc| find_break_parent(doc, has_line)),
+        Doc::Indent(doc) | Doc::IndentIfBreak(doc) => find_break_parent(doc, has_line),
+        _ => None,
+    }
+}
+
+/// Removes the line breaks the passed documents end with, like the ones after
+/// the last comments of a block.
+pub(crate) fn trim_hard_lines(docs: &mut Vec<Doc>) {
+    while matches!(docs.last(), Some(Doc::HardLine | Doc::Nil)) {
+        docs.pop();
+    }
+}
+
+/// Whether or not the passed line suffix ends with a single-line comment.
+fn ends_with_single_line_comment(doc: &Doc) -> bool {
+    match doc {
+        Doc::Comment(text) => text.strip_prefix("--").is_some_and(|comment| {
+            !comment
+                .strip_prefix('[')
+                .is_some_and(|comment| comment.trim_start_matches('=').starts_with('['))
+        }),
+        Doc::Concat(docs) => docs
+            .iter()
+            .rev()
+            .find(|doc| !doc.is_empty())
+            .is_some_and(ends_with_single_line_comment),
+        _ => false,
+    }
+}
+
+/// The width of the passed text once displayed, in columns, as counted against
+/// [`Config::column_width`]. Wide characters, like most emojis, take two
+/// columns, and tabs take [`Config::tab_size`] ones.
+pub(crate) fn width(text: &str, config: &Config) -> usize {
+    if text.is_ascii() && !text.contains('\t') {
+        return text.len();
+    }
+
+    text.chars()
+        .map(|char| match char {
+            '\t' => config.tab_size,
+            _ => char.width().unwrap_or(0),
+        })
+        .sum()
+}
+
+/// Whether the line breaks of a group are printed or not.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    /// The line breaks are printed.
+    Break,
+
+    /// The line breaks aren't printed.
+    Flat,
+}
+
+/// A document to print, with the indentation and mode it's printed with.
+type Command<'a> = (Indentation, Mode, &'a Doc);
+
+/// The state of the printer.
+struct Printer<'a> {
+    /// The config to print with.
+    config: &'a Config,
+
+    /// The printed code.
+    output: String,
+
+    /// The width of the current line, with its indentation.
+    width: usize,
+
+    /// The documents to print before the next line break.
+    line_suffixes: Vec<Command<'a>>,
+
+    /// Whether or not a line break was printed in a group that doesn't break,
+    /// in which case the groups after it must check again if they fit.
+    should_remeasure: bool,
+}
+
+impl<'a> Printer<'a> {
+    /// Prints a line break and the passed indentation, removing the whitespace
+    /// the line ends with.
+    fn push_line_break(&mut self, indentation: Indentation) {
+        self.output
+            .truncate(self.output.trim_end_matches([' ', '\t']).len());
+        self.output.push_str(self.config.newline_style.as_str());
+
+        let indentation = self.config.indent_style.to_string(indentation, self.config);
+        self.width = width(&indentation, self.config);
+        self.output.push_str(&indentation);
+    }
+
+    /// Whether or not the current line only has its indentation.
+    fn is_at_line_start(&self) -> bool {
+        let output = self.output.trim_end_matches([' ', '\t']);
+
+        output.is_empty() || output.ends_with('\n')
+    }
+
+    /// Pushes the line suffixes to `commands`, to print them next. The ones
+    /// ending with a single-line comment are printed last, as it comments out
+    /// the rest of the line.
+    fn flush_line_suffixes(&mut self, commands: &mut Vec<Command<'a>>) {
+        self.line_suffixes
+            .sort_by_key(|(_, _, doc)| ends_with_single_line_comment(doc));
+        commands.extend(self.line_suffixes.drain(..).rev());
+    }
+
+    /// Prints a space, unless the line already ends with whitespace.
+    fn push_space(&mut self) {
+        if !self.output.is_empty() && !self.output.ends_with(char::is_whitespace) {
+            self.output.push(' ');
+            self.width += 1;
+        }
+    }
+
+    /// Whether or not the passed command fits on the current line if printed
+    /// flat, along with the commands after it up to their first line break. It
+    /// doesn't if a single-line comment in a line suffix is followed by a line
+    /// break of the group and then by text, as breaking the group keeps the
+    /// comment next to the code before it instead of merging it with the next
+    /// comments at the end of the line.
+    fn fits(&self, next: Command<'a>, rest: &[Command<'a>]) -> bool {
+        let mut remaining = self.config.column_width as isize - self.width as isize;
+        let mut stack = vec![(next.1, next.2)];
+        let mut rest_index = rest.len();
+        let mut has_line_suffix = false;
+        let mut has_single_line_comment = false;
+        let mut has_line_after_comment = false;
+        let mut has_text = false;
+        // Spaces are only counted before text, as they're only printed once.
+        let mut has_space = false;
+
+        while remaining >= 0 {
+            let (mode, doc) = match stack.pop() {
+                Some(command) => command,
+                None if rest_index == 0 => return true,
+                None => {
+                    rest_index -= 1;
+                    (rest[rest_index].1, rest[rest_index].2)
+                }
+            };
+
+            match doc {
+                Doc::Nil => {}
+                // The line suffix would end up after the first line of the text.
+                Doc::Text(text) if has_line_suffix && text.contains('\n') => return false,
+                Doc::Text(text) if text.is_empty() => {}
+                Doc::Text(_) if has_line_after_comment => return false,
+                Doc::Text(text) => {
+                    if has_space {
+                        remaining -= 1;
+                        has_space = false;
+                    }
+
+                    match text.split_once('\n') {
+                        Some((first_line, _)) => {
+                            return remaining >= width(first_line, self.config) as isize;
+                        }
+                        None => {
+                            remaining -= width(text, self.config) as isize;
+                            has_text = true;
+                        }
+                    }
+                }
+                // Neither is the space before it.
+                Doc::Comment(_) => has_space = false,
+                Doc::Space => has_space = true,
+                Doc::Line | Doc::SoftLine if mode == Mode::Break => return true,
+                Doc::Line => {
+                    has_space = true;
+                    has_text = true;
+                    has_line_after_comment = has_single_line_comment;
+                }
+                Doc::SoftLine => has_line_after_comment = has_single_line_comment,
+                Doc::HardLine | Doc::TokenLine | Doc::LineStart => return true,
+                Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (mode, doc))),
+                Doc::Indent(doc) | Doc::IndentIfBreak(doc) => stack.push((mode, doc)),
+                Doc::Group { contents, breaks } => {
+                    stack.push((if *breaks { Mode::Break } else { mode }, contents));
+                }
+                Doc::IfBreak { broken, flat } => {
+                    stack.push((mode, if mode == Mode::Break { broken } else { flat }));
+                }
+                Doc::LineSuffix(doc) => {
+                    has_line_suffix = true;
+                    has_single_line_comment |= ends_with_single_line_comment(doc);
+                }
+                // What it's before must be on its own line.
+                Doc::BreakParent if has_text => return false,
+                Doc::BreakParent => {}
+            }
+        }
+
+        false
+    }
+
+    /// Prints the passed document.
+    fn print(&mut self, doc: &'a Doc, indentation: Indentation) {
+        let mut commands: Vec<Command<'a>> = vec![(indentation, Mode::Break, doc)];
+
+        while let Some((indentation, mode, doc)) = commands.pop() {
+            match doc {
+                Doc::Nil | Doc::BreakParent => {}
+                // The text starts on the next line, like skipped code kept with
+                // the line break before it, so the line suffixes end this one.
+                Doc::Text(text) if text.starts_with('\n') && !self.line_suffixes.is_empty() => {
+                    commands.push((indentation, mode, doc));
+                    self.flush_line_suffixes(&mut commands);
+                    continue;
+                }
+                Doc::Text(text) | Doc::Comment(text) => {
+                    self.output.push_str(text);
+
+                    match text.rsplit_once('\n') {
+                        Some((_, last_line)) => self.width = width(last_line, self.config),
+                        None => self.width += width(text, self.config),
+                    }
+                }
+                Doc::Space => self.push_space(),
+                Doc::Line if mode == Mode::Flat => self.push_space(),
+                Doc::SoftLine if mode == Mode::Flat => {}
+                Doc::LineStart if self.is_at_line_start() => {}
+                Doc::Line | Doc::SoftLine | Doc::HardLine | Doc::TokenLine | Doc::LineStart => {
+                    if !self.line_suffixes.is_empty() && !matches!(doc, Doc::TokenLine) {
+                        commands.push((indentation, mode, doc));
+                        self.flush_line_suffixes(&mut commands);
+                        continue;
+                    }
+
+                    if mode == Mode::Flat {
+                        self.should_remeasure = true;
+                    }
+                    self.push_line_break(indentation);
+                }
+                Doc::Concat(docs) => {
+                    commands.extend(docs.iter().rev().map(|doc| (indentation, mode, doc)));
+                }
+                Doc::Indent(doc) => commands.push((indentation + 1, mode, doc)),
+                Doc::IndentIfBreak(doc) if mode == Mode::Break => {
+                    commands.push((indentation + 1, mode, doc));
+                }
+                Doc::IndentIfBreak(doc) => commands.push((indentation, mode, doc)),
+                Doc::Group { contents, breaks } => {
+                    let mode = if *breaks {
+                        Mode::Break
+                    } else if mode == Mode::Flat && !self.should_remeasure {
+                        Mode::Flat
+                    } else {
+                        self.should_remeasure = false;
+
+                        if self.fits((indentation, Mode::Flat, contents), &commands) {
+                            Mode::Flat
+                        } else {
+                            Mode::Break
+                        }
+                    };
+
+                    commands.push((indentation, mode, contents));
+                }
+                Doc::IfBreak { broken, flat } => {
+                    let contents = if mode == Mode::Break { broken } else { flat };
+                    commands.push((indentation, mode, contents));
+                }
+                Doc::LineSuffix(doc) => self.line_suffixes.push((indentation, mode, doc)),
+            }
+
+            if commands.is_empty() {
+                self.flush_line_suffixes(&mut commands);
+            }
+        }
+    }
+}
+
+/// Prints the passed document with the passed [`Config`]. `indentation` is the
+/// one of its lines, the first one isn't indented as it's after the code before
+/// it, which is assumed to be that indentation.
+pub(crate) fn print(doc: &Doc, indentation: Indentation, config: &Config) -> String {
+    let mut printer = Printer {
+        config,
+        output: String::new(),
+        width: width(&config.indent_style.to_string(indentation, config), config),
+        line_suffixes: Vec::new(),
+        should_remeasure: false,
+    };
+    printer.print(doc, indentation);
+
+    printer.output
+}
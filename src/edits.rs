@@ -0,0 +1,173 @@
+//! Turning formatted code into the minimal edits that produce it from the
+//! original code.
+
+use similar::{DiffOp, DiffTag, TextDiff};
+use std::ops::Range;
+
+/// A replacement of a part of the original code.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct TextEdit {
+    /// The byte range of the original code to replace. Empty for insertions.
+    pub range: Range<usize>,
+
+    /// The text to replace it with. Empty for deletions.
+    pub new_text: String,
+}
+
+/// The byte offset of the start of each token, and of the end of the last one.
+fn token_offsets(tokens: &[&str], start: usize) -> Vec<usize> {
+    let mut offsets = Vec::with_capacity(tokens.len() + 1);
+    let mut offset = start;
+
+    offsets.push(offset);
+    for token in tokens {
+        offset += token.len();
+        offsets.push(offset);
+    }
+
+    offsets
+}
+
+/// Pushes the passed edit, merging it with the last one if they touch, as
+/// nothing is left unchanged between them.
+fn push_edit(edits: &mut Vec<TextEdit>, range: Range<usize>, new_text: &str) {
+    match edits.last_mut() {
+        Some(edit) if edit.range.end == range.start => {
+            edit.range.end = range.end;
+            edit.new_text.push_str(new_text);
+        }
+        _ => edits.push(TextEdit {
+            range,
+            new_text: new_text.to_string(),
+        }),
+    }
+}
+
+/// Gets the edits of the passed [`DiffOp`]s, where the tokens of `old` start at
+/// the byte offset `start` of the original code.
+fn push_edits(
+    edits: &mut Vec<TextEdit>,
+    ops: &[DiffOp],
+    old: &[&str],
+    new: &[&str],
+    start: usize,
+) {
+    let old_offsets = token_offsets(old, start);
+
+    for op in ops {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            continue;
+        }
+
+        push_edit(
+            edits,
+            old_offsets[old_range.start]..old_offsets[old_range.end],
+            &new[new_range].concat(),
+        );
+    }
+}
+
+/// Computes the minimal edits that turn `original` into `formatted`, sorted by
+/// position. Changed lines are found first, then only the changed characters
+/// in them are replaced, so that unchanged code, like the position of the
+/// cursor in an editor, isn't touched.
+pub fn compute_edits(original: &str, formatted: &str) -> Vec<TextEdit> {
+    let line_diff = TextDiff::from_lines(original, formatted);
+    let old_lines = line_diff.old_slices();
+    let new_lines = line_diff.new_slices();
+    let line_offsets = token_offsets(old_lines, 0);
+
+    let mut edits = Vec::new();
+
+    for op in line_diff.ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            continue;
+        }
+
+        let old = old_lines[old_range.clone()].concat();
+        let new = new_lines[new_range].concat();
+
+        let char_diff = TextDiff::from_chars(&old, &new);
+        push_edits(
+            &mut edits,
+            char_diff.ops(),
+            char_diff.old_slices(),
+            char_diff.new_slices(),
+            line_offsets[old_range.start],
+        );
+    }
+
+    edits
+}
+
+/// Applies the passed edits, as returned by [`compute_edits`], to `original`.
+pub fn apply_edits(original: &str, edits: &[TextEdit]) -> String {
+    let mut code = String::with_capacity(original.len());
+    let mut cursor = 0;
+
+    for edit in edits {
+        code.push_str(&original[cursor..edit.range.start]);
+        code.push_str(&edit.new_text);
+        cursor = edit.range.end;
+    }
+
+    code.push_str(&original[cursor..]);
+
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TextEdit, apply_edits, compute_edits};
+
+    #[test]
+    fn edits_give_the_formatted_code() {
+        for (original, formatted) in [
+            ("local  é = 'ü'\r\nprint( é )\r\n", "local é = \"ü\"\nprint(é)\n"),
+            ("local é = 1\n", "local è = 1\n"),
+            ("print('😀', '😀')", "print(\"😀\", \"😀\")\n"),
+            ("a()\n\n\n\nb()", "a()\n\nb()\n"),
+            ("a()\n", "a()\nb()\n"),
+            ("", "a()\n"),
+            ("a()\n", ""),
+        ] {
+            let edits = compute_edits(original, formatted);
+
+            for edit in &edits {
+                assert!(original.is_char_boundary(edit.range.start), "{edit:?}");
+                assert!(original.is_char_boundary(edit.range.end), "{edit:?}");
+            }
+            assert_eq!(apply_edits(original, &edits), formatted, "{original:?}");
+        }
+    }
+
+    #[test]
+    fn only_changed_characters_are_replaced() {
+        assert_eq!(
+            compute_edits("x = 'é'\n", "x = \"é\"\n"),
+            [
+                TextEdit {
+                    range: 4..5,
+                    new_text: "\"".to_string(),
+                },
+                TextEdit {
+                    range: 7..8,
+                    new_text: "\"".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn insertions_at_the_end_are_empty_ranges() {
+        assert_eq!(
+            compute_edits("a()", "a()\n"),
+            [TextEdit {
+                range: 3..3,
+                new_text: "\n".to_string(),
+            }]
+        );
+    }
+}
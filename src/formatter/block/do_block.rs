@@ -2,17 +2,17 @@
 
 use luau_parser::types::DoBlock;
 
-use crate::{
-    config::Config,
-    traits::{Format, Indentation},
-};
+use crate::{config::Config, doc::Doc, traits::Format};
 
-impl Format for DoBlock {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let mut string = self.do_keyword.format(indentation, config);
-        string.push_str(&self.body.format(indentation + 1, config));
-        string.push_str(&self.end_keyword.format(indentation, config));
+use super::{format_body, format_closing_keyword};
 
-        string
+impl Format for DoBlock {
+    fn format(&self, config: &Config) -> Doc {
+        vec![
+            self.do_keyword.format(config),
+            format_body(&self.body, config),
+            format_closing_keyword(&self.body, &self.end_keyword, config),
+        ]
+        .into()
     }
 }
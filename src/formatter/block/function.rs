@@ -12,15 +12,14 @@ use luau_parser::types::{
 
 use crate::{
     config::Config,
-    formatter::TokenFormatType,
-    traits::{Expand, ExpandWithArgs, Format, FormatWithArgs, Indentation},
+    doc::Doc,
+    traits::{Format, FormatWithArgs},
 };
 
 impl Format for LocalFunction {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         format_function!(
             self,
-            indentation,
             config,
             let keyword = local_keyword;
             let name = function_name;
@@ -29,42 +28,35 @@ impl Format for LocalFunction {
 }
 
 impl Format for GlobalFunctionName {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         match self {
-            GlobalFunctionName::SimpleName(token) => {
-                token.format_with(indentation, config, TokenFormatType::Name)
-            }
+            GlobalFunctionName::SimpleName(token) => token.format(config),
             GlobalFunctionName::Table {
                 table,
                 keys,
                 method,
             } => {
-                let mut string = table.format_with(indentation, config, TokenFormatType::Name);
+                let mut docs = vec![table.format(config)];
 
                 for key in keys.iter() {
-                    string.push_str(&key.format(indentation, config));
+                    docs.push(key.format(config));
                 }
 
                 if let Some(method) = method {
-                    string.push_str(&method.0.format(indentation, config));
-                    string.push_str(&method.1.format_with(
-                        indentation,
-                        config,
-                        TokenFormatType::Method,
-                    ));
+                    docs.push(method.0.format(config));
+                    docs.push(method.1.format(config));
                 }
 
-                string
+                docs.into()
             }
         }
     }
 }
 
 impl Format for GlobalFunction {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         format_function!(
             self,
-            indentation,
             config,
             let name = function_name;
         )
@@ -73,44 +65,43 @@ impl Format for GlobalFunction {
 
 impl Format for Attribute {
     #[inline]
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        self.at.format(indentation, config)
-            + &self
-                .attribute
-                .format_with(indentation, config, TokenFormatType::Name)
+    fn format(&self, config: &Config) -> Doc {
+        vec![
+            self.at.format(config),
+            self.attribute.format(config),
+        ]
+        .into()
     }
 }
 impl Format for Vec<Attribute> {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        self.iter().fold(String::new(), |str, item| {
-            str + &item.format(indentation, config) + config.newline_style.as_str()
-        })
+    fn format(&self, config: &Config) -> Doc {
+        self.iter()
+            .flat_map(|item| [item.format(config), Doc::HardLine])
+            .collect::<Vec<Doc>>()
+            .into()
     }
 }
 
 impl Format for Parameter {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         if let Some(r#type) = self.r#type.as_ref() {
-            let mut string = self
-                .name
-                .format_with(indentation, config, TokenFormatType::Name);
-            string.push_str(&self.colon.format(indentation, config));
-            string.push(' ');
-            string.push_str(&r#type.format(indentation, config));
-
-            string
+            vec![
+                self.name.format(config),
+                self.colon.format(config),
+                Doc::Space,
+                r#type.format(config),
+            ]
+            .into()
         } else {
-            self.name
-                .format_with(indentation, config, TokenFormatType::Name)
+            self.name.format(config)
         }
     }
 }
 
 impl Format for TypeFunction {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         format_function!(
             self,
-            indentation,
             config,
             let export = export_keyword;
             let keyword = type_keyword;
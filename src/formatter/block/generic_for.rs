@@ -4,21 +4,24 @@ use luau_parser::types::GenericFor;
 
 use crate::{
     config::Config,
-    traits::{Format, FormatWithArgs, Indentation},
+    doc::Doc,
+    formatter::list::ListSeparator,
+    traits::{Format, FormatWithArgs},
 };
 
 impl Format for GenericFor {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let mut string = self.for_keyword.format(indentation, config);
-        string.push(' ');
-        string.push_str(&self.names.format_with(indentation, config, ", "));
-        string.push(' ');
-        string.push_str(&self.in_keyword.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.expressions.format_with(indentation, config, ", "));
-        string.push(' ');
-        string.push_str(&self.do_block.format(indentation, config));
-
-        string
+    fn format(&self, config: &Config) -> Doc {
+        vec![
+            self.for_keyword.format(config),
+            Doc::Space,
+            self.names.format_with(config, ListSeparator::Space),
+            Doc::Space,
+            self.in_keyword.format(config),
+            Doc::Space,
+            self.expressions.format_with(config, ListSeparator::Space),
+            Doc::Space,
+            self.do_block.format(config),
+        ]
+        .into()
     }
 }
@@ -4,80 +4,119 @@
 //! * [`ElseStatement`]
 //! * [`ElseIfStatement`]
 
-use luau_parser::types::{ElseIfStatement, ElseStatement, Expression, IfStatement, Pointer};
+use luau_parser::{
+    prelude::Token,
+    types::{ElseIfStatement, ElseStatement, Expression, IfStatement},
+};
 
 use crate::{
     config::Config,
-    traits::{Format, Indentation},
+    doc::Doc,
+    formatter::{expression::format_binary_expression, format_without_leading_comments},
+    traits::Format,
 };
 
-/// Handles formatting the condition of a [`IfStatement`] or an [`ElseIfStatement`].
-fn handle_condition(
-    string: &mut String,
-    condition: &Pointer<Expression>,
-    indentation: Indentation,
-    config: &Config,
-) {
-    let condition = condition.format(indentation, config);
-    // We check for newlines instead of the config's newline_style since the user
-    // may not be using that style by default. \n is guaranteed to exist in any
-    // new line.
-    let is_condition_multiline = condition.contains('\n');
-
-    if is_condition_multiline {
-        string.push_str(
-            &(config.newline_style.to_string()
-                + &config.indent_style.to_string(indentation + 1, config)),
-        );
-    } else {
-        string.push(' ');
-    }
+use super::{format_body, format_body_end, format_closing_keyword};
 
-    string.push_str(&condition);
+/// Formats the condition of an [`IfStatement`] or an [`ElseIfStatement`] between
+/// the passed formatted keyword and its `then` keyword. If it doesn't fit on the
+/// line, it's put on its own lines between them, indented once more.
+fn format_condition(
+    keyword: Doc,
+    condition: &Expression,
+    then_keyword: &Token,
+    config: &Config,
+) -> Doc {
+    let condition = match condition {
+        Expression::BinaryExpression { .. } => format_binary_expression(condition, false, config),
+        _ => condition.format(config),
+    };
 
-    if is_condition_multiline {
-        string.push_str(
-            &(config.newline_style.to_string()
-                + &config.indent_style.to_string(indentation, config)),
-        );
-    } else {
-        string.push(' ');
-    }
+    Doc::group(
+        vec![
+            keyword,
+            Doc::indent_if_break(vec![Doc::Line, condition].into()),
+            Doc::Line,
+            then_keyword.format(config),
+        ]
+        .into(),
+    )
 }
 
 impl Format for IfStatement {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let mut string = self.if_keyword.format(indentation, config);
-        handle_condition(&mut string, &self.condition, indentation, config);
-        string.push_str(&self.then_keyword.format(indentation, config));
-        string.push_str(&self.body.format(indentation + 1, config));
+    fn format(&self, config: &Config) -> Doc {
+        let mut docs = vec![
+            format_condition(
+                self.if_keyword.format(config),
+                &self.condition,
+                &self.then_keyword,
+                config,
+            ),
+            format_body(&self.body, config),
+        ];
+
+        // Each keyword closes the body before it, so it's formatted with it.
+        let mut previous_body = &self.body;
 
         for else_if_statement in self.else_if_statements.iter() {
-            string.push_str(&else_if_statement.format(indentation, config));
+            docs.push(format_body_end(
+                previous_body,
+                &else_if_statement.elseif_keyword,
+                config,
+            ));
+            docs.push(format_condition(
+                format_without_leading_comments(&else_if_statement.elseif_keyword, config),
+                &else_if_statement.condition,
+                &else_if_statement.then_keyword,
+                config,
+            ));
+            docs.push(format_body(&else_if_statement.body, config));
+
+            previous_body = &else_if_statement.body;
+        }
+
+        if let Some(else_statement) = &self.else_statement {
+            docs.push(format_closing_keyword(
+                previous_body,
+                &else_statement.else_keyword,
+                config,
+            ));
+            docs.push(format_body(&else_statement.body, config));
+
+            previous_body = &else_statement.body;
         }
-        string.push_str(&self.else_statement.format(indentation, config));
-        string.push_str(&self.end_keyword.format(indentation, config));
 
-        string
+        docs.push(format_closing_keyword(
+            previous_body,
+            &self.end_keyword,
+            config,
+        ));
+
+        docs.into()
     }
 }
 
 impl Format for ElseIfStatement {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let mut string = self.elseif_keyword.format(indentation, config);
-        handle_condition(&mut string, &self.condition, indentation, config);
-        string.push_str(&self.then_keyword.format(indentation, config));
-        string.push_str(&self.body.format(indentation + 1, config));
-
-        string
+    fn format(&self, config: &Config) -> Doc {
+        vec![
+            format_condition(
+                self.elseif_keyword.format(config),
+                &self.condition,
+                &self.then_keyword,
+                config,
+            ),
+            format_body(&self.body, config),
+        ]
+        .into()
     }
 }
 
 impl Format for ElseStatement {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let mut string = self.else_keyword.format(indentation, config);
-        string.push_str(&self.body.format(indentation + 1, config));
-
-        string
+    fn format(&self, config: &Config) -> Doc {
+        vec![
+            self.else_keyword.format(config),
+            format_body(&self.body, config),
+        ]
+        .into()
     }
 }
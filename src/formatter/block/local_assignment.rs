@@ -4,22 +4,26 @@ use luau_parser::types::LocalAssignment;
 
 use crate::{
     config::Config,
-    traits::{Format, FormatWithArgs, Indentation},
+    doc::Doc,
+    formatter::list::ListSeparator,
+    traits::{Format, FormatWithArgs},
 };
 
 impl Format for LocalAssignment {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let mut string = self.local_token.format(indentation, config);
-        string.push(' ');
-        string.push_str(&self.name_list.format_with(indentation, config, ", "));
+    fn format(&self, config: &Config) -> Doc {
+        let mut docs = vec![
+            self.local_token.format(config),
+            Doc::Space,
+            self.name_list.format_with(config, ListSeparator::Space),
+        ];
 
         if self.equal_token.is_some() {
-            string.push(' ');
-            string.push_str(&self.equal_token.format(indentation, config));
-            string.push(' ');
-            string.push_str(&self.expressions.format_with(indentation, config, ", "));
+            docs.push(Doc::Space);
+            docs.push(self.equal_token.format(config));
+            docs.push(Doc::Space);
+            docs.push(self.expressions.format_with(config, ListSeparator::Space));
         }
 
-        string
+        docs.into()
     }
 }
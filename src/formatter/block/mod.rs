@@ -14,22 +14,46 @@ mod statement;
 mod type_definition;
 mod while_loop;
 
-use get_block_type::{BlockType, get_block_type, get_name_from_token, get_name_from_var};
+pub(crate) use get_block_type::{BlockType, get_block_type};
+
+use get_block_type::{get_name_from_token, get_name_from_var};
 use get_trailing_trivia::{
     get_trailing_trivia_expr, get_trailing_trivia_function_call, get_trailing_trivia_token,
     get_trailing_trivia_type,
 };
 use luau_parser::{
-    prelude::{Block, Statement, TerminationStatement, Token, Trivia},
+    prelude::{Block, Lexer, Statement, TerminationStatement, Token, Trivia},
     types::{Pointer, Print},
 };
 
 use crate::{
     config::{Config, Semicolon},
+    doc::{self, Doc, trim_hard_lines},
     traits::{Format, FormatWithArgs, Indentation},
 };
 
-use super::trivia::TriviaFormattingType;
+use super::{
+    format_without_leading_comments,
+    trivia::{
+        TriviaFormattingType, count_line_breaks, format_own_line_comments, split_at_line_break,
+    },
+};
+
+/// Gets the statements of the passed [`Block`], without the
+/// [`Statement::EndOfFile`] that's put after its [`TerminationStatement`], if
+/// any. The comments it holds are already in the trailing trivia of the
+/// [`TerminationStatement`].
+pub(crate) fn get_statements(block: &Block) -> &[(Pointer<Statement>, Option<Token>)] {
+    match block.statements.split_last() {
+        Some(((statement, _), statements))
+            if block.last_statement.is_some()
+                && matches!(**statement, Statement::EndOfFile(_)) =>
+        {
+            statements
+        }
+        _ => &block.statements,
+    }
+}
 
 /// Get the trailing trivia of a [`Statement`].
 #[inline]
@@ -102,76 +126,141 @@ fn get_trailing_trivia_last_statement(last_statement: &TerminationStatement) ->
     }
 }
 
-/// Get the trailing comments of a [`Statement`] or a [`TerminationStatement`].
+/// Gets the trailing trivia of a [`Statement`] or a [`TerminationStatement`], or
+/// of the semicolon after it if any.
 #[inline]
-fn get_trailing_comments<T, F>(
-    (statement, semicolon): &(T, Option<Token>),
-    indentation: Indentation,
-    config: &Config,
+fn get_final_trivia<'a, T, F>(
+    (statement, semicolon): &'a (T, Option<Token>),
     get_trailing_trivia: F,
-) -> String
+) -> &'a [Trivia]
 where
-    F: FnOnce(&T) -> &[Trivia],
+    F: FnOnce(&'a T) -> &'a [Trivia],
 {
     if let Some(semicolon) = semicolon {
         get_trailing_trivia_token(semicolon)
     } else {
         get_trailing_trivia(statement)
     }
-    .format_with(indentation, config, TriviaFormattingType::CommentsOnly)
 }
 
-/// Handles the semicolon character that may or may not be after a [`Statement`] or
-/// a [`TerminationStatement`].
-fn handle_semicolon<F>(
-    formatted_code: &mut String,
-    semicolon: &Option<Token>,
-    indentation: Indentation,
+/// Get the trailing comments of a [`Statement`] or a [`TerminationStatement`] that
+/// are on their own lines. These are the last comments of the block.
+#[inline]
+fn get_trailing_comments<'a, T, F>(
+    statement: &'a (T, Option<Token>),
     config: &Config,
-    get_trailing_spaces: F,
-) -> String
+    get_trailing_trivia: F,
+) -> Vec<Doc>
 where
-    F: FnOnce() -> String,
+    F: FnOnce(&'a T) -> &'a [Trivia],
 {
-    let spaces = semicolon
-        .as_ref()
-        .map(|semicolon| {
-            get_trailing_trivia_token(semicolon).format_with(
-                indentation,
-                config,
-                TriviaFormattingType::SpacesOnly,
-            )
-        })
-        .unwrap_or_else(get_trailing_spaces);
+    format_own_line_comments(
+        split_at_line_break(get_final_trivia(statement, get_trailing_trivia)).1,
+        config,
+    )
+}
 
-    let new_lines = spaces.matches('\n').count();
+/// Gets the number of line breaks to put after a [`Statement`] or a
+/// [`TerminationStatement`], `final_trivia` being its trailing trivia, or the one
+/// of the semicolon after it.
+fn get_spaces(final_trivia: &[Trivia], config: &Config) -> usize {
+    let new_lines = count_line_breaks(final_trivia);
 
-    let spaces = if config.keep_statements_spacing {
-        spaces
+    if config.keep_statements_spacing {
+        new_lines.max(1)
     } else if new_lines >= 2 {
         // Maximum of 2 new lines (1 empty line) if we
         // don't preserve user spacing.
-        config.newline_style.to_string().repeat(2)
+        2
     } else {
-        config.newline_style.to_string()
-    };
+        1
+    }
+}
 
-    match config.semicolon {
-        Semicolon::Keep => {
-            formatted_code.push_str(&semicolon.format(indentation, config));
-        }
-        Semicolon::Always if semicolon.is_some() => {
-            formatted_code.push_str(&semicolon.format(indentation, config));
+/// Pushes the passed formatted statement to `docs`, without the space after the
+/// comments it may end with, as the semicolon may be put right after it.
+#[inline]
+fn push_statement(docs: &mut Vec<Doc>, mut statement: Doc) {
+    statement.trim_trailing_space();
+    docs.push(statement);
+}
+
+/// Pushes the passed number of line breaks to `docs`.
+#[inline]
+fn push_line_breaks(docs: &mut Vec<Doc>, count: usize) {
+    docs.extend((0..count).map(|_| Doc::HardLine));
+}
+
+/// Pushes the passed code, kept as-is, to `docs`. The line break it may end with
+/// is pushed as a [`Doc::HardLine`], so the code after it is indented.
+fn push_raw(docs: &mut Vec<Doc>, code: &str) {
+    match code.strip_suffix('\n') {
+        Some(code) => {
+            docs.push(Doc::text(code.strip_suffix('\r').unwrap_or(code)));
+            docs.push(Doc::HardLine);
         }
-        Semicolon::Always => {
-            formatted_code.push(';');
+        None => docs.push(Doc::text(code)),
+    }
+}
+
+/// Formats the semicolon character that may or may not be after a [`Statement`]
+/// or a [`TerminationStatement`]. The comments on the same line as the end of
+/// the statement end up after it, as they're put at the end of the line.
+/// `can_add_semicolon` is `false` for statements that can't be followed by one,
+/// like [`Statement::EndOfFile`].
+fn format_semicolon(semicolon: &Option<Token>, can_add_semicolon: bool, config: &Config) -> Doc {
+    let has_semicolon = match config.semicolon {
+        Semicolon::Keep => semicolon.is_some(),
+        Semicolon::Always => semicolon.is_some() || can_add_semicolon,
+        Semicolon::Never => false,
+    };
+
+    let mut docs = Vec::new();
+    if has_semicolon {
+        docs.push(Doc::text(";"));
+    }
+
+    let Some(semicolon) = semicolon else {
+        return docs.into();
+    };
+
+    // Comments between the statement and the semicolon, on their own lines.
+    let mut leading_comments =
+        format_own_line_comments(split_at_line_break(&semicolon.leading_trivia).1, config);
+    trim_hard_lines(&mut leading_comments);
+
+    let trailing_comments = semicolon
+        .trailing_trivia
+        .format_with(config, TriviaFormattingType::TrailingComments);
+
+    if !leading_comments.is_empty() {
+        docs.push(Doc::HardLine);
+        docs.extend(leading_comments);
+
+        if !trailing_comments.is_empty() {
+            docs.push(Doc::HardLine);
         }
-        _ => (),
     }
+    docs.push(trailing_comments);
 
-    formatted_code.push_str(&spaces);
+    docs.into()
+}
+
+/// Removes the comments on the same line as the previous token, and the spaces
+/// around them, from the start of the passed printed statement, as they're
+/// formatted with that token.
+fn without_previous_comments(code: &str) -> &str {
+    let token = Lexer::new(code).next_token();
+    let (same_line, _) = split_at_line_break(&token.leading_trivia);
+    let length = same_line
+        .iter()
+        .map(|trivia| match trivia {
+            Trivia::Spaces(spaces) => spaces.len(),
+            Trivia::Comment(comment) => comment.print().len(),
+        })
+        .sum();
 
-    spaces
+    &code[length..]
 }
 
 /// Gets the name (identefier) from a [`Statement`]
@@ -188,255 +277,274 @@ fn get_name_from_statement(statement: &Statement) -> String {
 }
 
 /// Arranges the passed statements in alphabetical order (by first variable name)
-/// and appends them to `formatted_code`.
+/// and pushes them to `docs`.
 fn arrange_statements(
-    formatted_code: &mut String,
+    docs: &mut Vec<Doc>,
     statements: &[(Pointer<Statement>, Option<Token>)],
-    indentation: Indentation,
     config: &Config,
-    spacing: &str,
 ) {
     let mut statements_sorted = statements.to_vec();
 
-    statements_sorted
-        .sort_by(|a, b| get_name_from_statement(&a.0).cmp(&get_name_from_statement(&b.0)));
-
-    for (i, (statement, _)) in statements_sorted.iter().enumerate() {
-        formatted_code.push_str(&statement.format(indentation, config));
-
-        handle_semicolon(
-            &mut *formatted_code,
-            &statements[i].1,
-            indentation,
-            config,
-            || {
-                get_trailing_trivia_statement(&statements[i].0).format_with(
-                    indentation,
-                    config,
-                    TriviaFormattingType::SpacesOnly,
-                )
-            },
+    statements_sorted.sort_by_key(|(statement, _)| get_name_from_statement(statement));
+
+    for (i, (statement, semicolon)) in statements_sorted.iter().enumerate() {
+        // Comments stay with their statement, but the spacing stays where it is.
+        push_statement(docs, statement.format(config));
+        docs.push(format_semicolon(semicolon, true, config));
+        push_line_breaks(
+            docs,
+            get_spaces(
+                get_final_trivia(&statements[i], |statement| {
+                    get_trailing_trivia_statement(statement)
+                }),
+                config,
+            ),
         );
+    }
+}
 
-        formatted_code.push_str(spacing);
+/// Formats the passed body of a statement, on the lines after it, indented once
+/// more.
+pub(crate) fn format_body(body: &Block, config: &Config) -> Doc {
+    if body.is_empty() {
+        Doc::Nil
+    } else {
+        Doc::indent(vec![Doc::HardLine, body.format(config)].into())
     }
 }
 
-impl Format for Block {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let indentation_spacing = config.indent_style.to_string(indentation, config);
+/// Formats what's between the passed body and the keyword closing it, like
+/// `end`, `else` or `until`, which is on its own line. The comments on their own
+/// lines before it are the last comments of the body, they're formatted with it
+/// unless it's empty, in which case they're indented like it here.
+pub(crate) fn format_body_end(body: &Block, keyword: &Token, config: &Config) -> Doc {
+    let mut docs = Vec::new();
 
-        if self.is_empty() {
-            return if indentation == 0 {
-                String::new()
-            } else {
-                config.newline_style.to_string()
-                    + &config.indent_style.to_string(indentation - 1, config)
-            };
+    if body.is_empty() {
+        let mut comments =
+            format_own_line_comments(split_at_line_break(&keyword.leading_trivia).1, config);
+        trim_hard_lines(&mut comments);
+
+        if !comments.is_empty() {
+            docs.push(Doc::indent(vec![Doc::HardLine, comments.into()].into()));
         }
+    }
 
-        let mut formatted_code = if indentation == 0 {
-            String::new()
-        } else {
-            config.newline_style.to_string() + &config.indent_style.to_string(indentation, config)
-        };
+    docs.push(Doc::HardLine);
 
-        let mut previous_block_type = BlockType::None;
-        let mut block_start_index = 0;
-        let last_index = self.statements.len() - 1;
-        let mut last_spaces = String::new();
-        let mut is_formatting = true;
-        let mut single_statement_skip = false;
-
-        for (i, (statement, semicolon)) in self.statements.iter().enumerate() {
-            let mut semicolon_string = String::new();
-            let mut should_format = true;
-            let spaces = handle_semicolon(
-                &mut semicolon_string,
-                semicolon,
-                indentation,
-                config,
-                || {
-                    get_trailing_trivia_statement(statement).format_with(
-                        indentation,
-                        config,
-                        TriviaFormattingType::SpacesOnly,
-                    )
-                },
-            );
+    docs.into()
+}
 
-            let statement_string = statement.print_without_final_trivia();
-            let trimmed_statement = statement_string.trim_start();
-            let mut offset = statement_string.len() - trimmed_statement.len();
+/// Formats the keyword closing the passed body on its own line, see
+/// [`format_body_end`].
+pub(crate) fn format_closing_keyword(body: &Block, keyword: &Token, config: &Config) -> Doc {
+    vec![
+        format_body_end(body, keyword, config),
+        format_without_leading_comments(keyword, config),
+    ]
+    .into()
+}
 
-            for line in trimmed_statement.lines() {
-                if !line.starts_with("--") {
-                    offset += line.len();
-                    continue;
-                }
+/// Formats the comments before the first token of the passed top-level block,
+/// the ones before its first line break. They aren't after any other token, so
+/// nothing else formats them.
+fn format_leading_comments(block: &Block, config: &Config) -> Vec<Doc> {
+    let code = match (get_statements(block).first(), &block.last_statement) {
+        (Some((statement, _)), _) => statement.print(),
+        (None, Some((last_statement, _))) => last_statement.print(),
+        (None, None) => return Vec::new(),
+    };
+    let token = Lexer::new(&code).next_token();
+    let (same_line, _) = split_at_line_break(&token.leading_trivia);
 
-                let trimmed = line.trim();
-                if trimmed == "--@luau-fmt skip" {
-                    single_statement_skip = true;
-                } else if trimmed == "--@luau-fmt skip-start" {
-                    should_format = false;
-                    is_formatting = false;
-                } else if trimmed == "--@luau-fmt skip-end" {
-                    is_formatting = true;
-
-                    if i > 0 {
-                        let leading_trivia = if let Some(semicolon) = &self.statements[i - 1].1 {
-                            semicolon.print_final_trivia()
-                        } else {
-                            self.statements[i - 1].0.print_final_trivia()
-                        };
-
-                        formatted_code.push_str(&leading_trivia[..offset]);
-                    }
-                    offset += line.len();
-                }
-            }
+    // The line break after them is kept too.
+    let end = (same_line.len() + 1).min(token.leading_trivia.len());
+    format_own_line_comments(&token.leading_trivia[..end], config)
+}
 
-            let block_type = if single_statement_skip {
-                BlockType::None
-            } else {
-                get_block_type(statement, config)
-            };
-            if block_type != previous_block_type
-                || (last_spaces.find('\n') != last_spaces.rfind('\n')
-                    && matches!(
-                        previous_block_type,
-                        BlockType::GetService | BlockType::Require
-                    ))
-            {
-                last_spaces = spaces;
-                match previous_block_type {
-                    BlockType::GetService | BlockType::Require => arrange_statements(
-                        &mut formatted_code,
-                        &self.statements[block_start_index..i],
-                        indentation,
-                        config,
-                        &indentation_spacing,
-                    ),
-                    BlockType::None => {}
-                }
+/// Formats the passed [`Block`] into code. `indentation` is the one of its
+/// lines, a block with none being a whole file.
+pub(crate) fn format_block(block: &Block, indentation: Indentation, config: &Config) -> String {
+    if block.is_empty() {
+        return String::new();
+    }
 
-                previous_block_type = block_type;
-
-                match block_type {
-                    BlockType::Require | BlockType::GetService => {
-                        if i == last_index {
-                            arrange_statements(
-                                &mut formatted_code,
-                                &self.statements[block_start_index..],
-                                indentation,
-                                config,
-                                &indentation_spacing,
-                            )
-                        } else {
-                            block_start_index = i;
-                            continue;
-                        }
-                    }
-                    _ => (),
-                }
+    let docs = if indentation == 0 {
+        format_leading_comments(block, config)
+    } else {
+        Vec::new()
+    };
 
-                block_start_index = i;
-            } else if block_type == BlockType::GetService || block_type == BlockType::Require {
-                last_spaces = spaces;
-                continue;
-            }
+    let mut formatted_code =
+        doc::print(&format_statements(block, docs, config), indentation, config);
+    formatted_code.truncate(formatted_code.trim_end().len());
 
-            if single_statement_skip {
-                formatted_code = formatted_code.trim_end().to_string();
-                formatted_code.push_str(&statement_string);
-                formatted_code.push_str(&semicolon_string);
+    if indentation == 0 && config.add_final_newline {
+        formatted_code.push_str(config.newline_style.as_str());
+    }
 
-                single_statement_skip = false;
+    formatted_code
+}
 
+/// Formats the statements of the passed [`Block`] after the passed documents,
+/// the comments before a top-level block, which the statements may remove the
+/// line breaks after.
+fn format_statements(block: &Block, mut docs: Vec<Doc>, config: &Config) -> Doc {
+    let statements = get_statements(block);
+    let mut previous_block_type = BlockType::None;
+    let mut block_start_index = 0;
+    let last_index = statements.len().saturating_sub(1);
+    let mut last_spaces = 0;
+    let mut is_formatting = true;
+    let mut single_statement_skip = false;
+
+    for (i, statement_and_semicolon) in statements.iter().enumerate() {
+        let (statement, semicolon) = statement_and_semicolon;
+        let mut should_format = true;
+        let final_trivia = get_final_trivia(statement_and_semicolon, |statement| {
+            get_trailing_trivia_statement(statement)
+        });
+        let spaces = get_spaces(final_trivia, config);
+
+        let statement_string = statement.print_without_final_trivia();
+        let trimmed_statement = statement_string.trim_start();
+
+        // The comments on the same line as the previous token are already
+        // formatted with it.
+        let own_statement_string = without_previous_comments(&statement_string);
+
+        for line in trimmed_statement.lines() {
+            if !line.starts_with("--") {
                 continue;
             }
 
-            if !is_formatting || !should_format {
-                if !should_format {
-                    if single_statement_skip {
-                        handle_semicolon(
-                            &mut formatted_code,
-                            &self.statements[i - 1].1,
-                            indentation,
-                            config,
-                            || {
-                                get_trailing_trivia_statement(&self.statements[i - 1].0)
-                                    .format_with(
-                                        indentation,
-                                        config,
-                                        TriviaFormattingType::SpacesOnly,
-                                    )
-                            },
-                        );
-                    }
+            let trimmed = line.trim();
+            if trimmed == "--@luau-fmt skip" {
+                single_statement_skip = true;
+            } else if trimmed == "--@luau-fmt skip-start" {
+                should_format = false;
+                is_formatting = false;
+            } else if trimmed == "--@luau-fmt skip-end" {
+                // The comments on the line of the last skipped statement
+                // are kept as-is too, with the line break after them.
+                if !is_formatting {
+                    let end = statement_string.len() - own_statement_string.len()
+                        + own_statement_string.find('\n').map_or(0, |index| index + 1);
+
+                    push_raw(&mut docs, &statement_string[..end]);
+                }
 
-                    formatted_code.push_str(trimmed_statement);
-                } else {
-                    formatted_code.push_str(&statement_string);
+                is_formatting = true;
+            }
+        }
+
+        let block_type = if single_statement_skip {
+            BlockType::None
+        } else {
+            get_block_type(statement, config)
+        };
+        if block_type != previous_block_type
+            || (last_spaces >= 2
+                && matches!(
+                    previous_block_type,
+                    BlockType::GetService | BlockType::Require
+                ))
+        {
+            last_spaces = spaces;
+            match previous_block_type {
+                BlockType::GetService | BlockType::Require => {
+                    arrange_statements(&mut docs, &statements[block_start_index..i], config)
                 }
+                BlockType::None => {}
+            }
 
-                formatted_code.push_str(&semicolon.print_without_final_trivia());
+            previous_block_type = block_type;
 
-                continue;
+            match block_type {
+                BlockType::Require | BlockType::GetService => {
+                    if i == last_index {
+                        arrange_statements(&mut docs, &statements[block_start_index..], config)
+                    } else {
+                        block_start_index = i;
+                        continue;
+                    }
+                }
+                _ => (),
             }
 
-            formatted_code.push_str(&statement.format(indentation, config));
-            formatted_code.push_str(&semicolon_string);
-            formatted_code.push_str(&indentation_spacing);
+            block_start_index = i;
+        } else if block_type == BlockType::GetService || block_type == BlockType::Require {
+            last_spaces = spaces;
+            continue;
         }
 
-        if is_formatting {
-            if let Some(last_statement) = &self.last_statement {
-                formatted_code.push_str(&last_statement.0.format(indentation, config));
+        if single_statement_skip {
+            trim_hard_lines(&mut docs);
+            docs.push(Doc::text(own_statement_string));
+            docs.push(Doc::Text(semicolon.print_without_final_trivia()));
+            docs.push(final_trivia.format_with(config, TriviaFormattingType::TrailingComments));
+            push_line_breaks(&mut docs, spaces);
 
-                handle_semicolon(
-                    &mut formatted_code,
-                    &last_statement.1,
-                    indentation,
-                    config,
-                    || {
-                        get_trailing_trivia_last_statement(&last_statement.0).format_with(
-                            indentation,
-                            config,
-                            TriviaFormattingType::SpacesOnly,
-                        )
-                    },
-                );
-
-                formatted_code.push_str(&indentation_spacing);
-                formatted_code.push_str(&get_trailing_comments(
-                    last_statement,
-                    indentation,
-                    config,
-                    |last_statement| get_trailing_trivia_last_statement(last_statement),
-                ))
+            single_statement_skip = false;
+
+            continue;
+        }
+
+        if !is_formatting || !should_format {
+            if !should_format {
+                docs.push(Doc::text(own_statement_string.trim_start()));
             } else {
-                formatted_code.push_str(&get_trailing_comments(
-                    self.statements.last().unwrap(),
-                    indentation,
-                    config,
-                    |statement| get_trailing_trivia_statement(statement),
-                ))
+                docs.push(Doc::Text(statement_string));
             }
+
+            docs.push(Doc::Text(semicolon.print_without_final_trivia()));
+
+            continue;
         }
 
-        formatted_code = formatted_code.trim_end().to_string();
+        push_statement(&mut docs, statement.format(config));
+        docs.push(format_semicolon(
+            semicolon,
+            !matches!(**statement, Statement::EndOfFile(_)),
+            config,
+        ));
+        push_line_breaks(&mut docs, spaces);
+    }
 
-        if indentation == 0 {
-            if config.add_final_newline {
-                formatted_code.push_str(config.newline_style.as_str());
-            }
+    if is_formatting {
+        if let Some(last_statement) = &block.last_statement {
+            push_statement(&mut docs, last_statement.0.format(config));
+            docs.push(format_semicolon(&last_statement.1, true, config));
+            push_line_breaks(
+                &mut docs,
+                get_spaces(
+                    get_final_trivia(last_statement, |last_statement| {
+                        get_trailing_trivia_last_statement(last_statement)
+                    }),
+                    config,
+                ),
+            );
+
+            docs.extend(get_trailing_comments(last_statement, config, |last_statement| {
+                get_trailing_trivia_last_statement(last_statement)
+            }));
         } else {
-            formatted_code.push_str(config.newline_style.as_str());
-            formatted_code.push_str(&config.indent_style.to_string(indentation - 1, config));
+            docs.extend(get_trailing_comments(
+                statements.last().unwrap(),
+                config,
+                |statement| get_trailing_trivia_statement(statement),
+            ));
         }
+    }
+
+    trim_hard_lines(&mut docs);
 
-        formatted_code
+    docs.into()
+}
+
+impl Format for Block {
+    #[inline]
+    fn format(&self, config: &Config) -> Doc {
+        format_statements(self, Vec::new(), config)
     }
 }
@@ -2,33 +2,32 @@
 
 use luau_parser::types::NumericalFor;
 
-use crate::{
-    config::Config,
-    traits::{Format, Indentation},
-};
+use crate::{config::Config, doc::Doc, traits::Format};
 
 impl Format for NumericalFor {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let mut string = self.for_keyword.format(indentation, config);
-        string.push(' ');
-        string.push_str(&self.variable.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.equal_keyword.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.start.format(indentation, config));
-        string.push_str(&self.start_comma.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.end.format(indentation, config));
+    fn format(&self, config: &Config) -> Doc {
+        let mut docs = vec![
+            self.for_keyword.format(config),
+            Doc::Space,
+            self.variable.format(config),
+            Doc::Space,
+            self.equal_keyword.format(config),
+            Doc::Space,
+            self.start.format(config),
+            self.start_comma.format(config),
+            Doc::Space,
+            self.end.format(config),
+        ];
 
         if let Some(step) = self.step.as_ref() {
-            string.push_str(&self.end_comma.format(indentation, config));
-            string.push(' ');
-            string.push_str(&step.format(indentation, config));
+            docs.push(self.end_comma.format(config));
+            docs.push(Doc::Space);
+            docs.push(step.format(config));
         }
 
-        string.push(' ');
-        string.push_str(&self.do_block.format(indentation, config));
+        docs.push(Doc::Space);
+        docs.push(self.do_block.format(config));
 
-        string
+        docs.into()
     }
 }
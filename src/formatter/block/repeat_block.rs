@@ -2,19 +2,19 @@
 
 use luau_parser::types::RepeatBlock;
 
-use crate::{
-    config::Config,
-    traits::{Format, Indentation},
-};
+use crate::{config::Config, doc::Doc, traits::Format};
 
-impl Format for RepeatBlock {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let mut string = self.repeat_keyword.format(indentation, config);
-        string.push_str(&self.body.format(indentation + 1, config));
-        string.push_str(&self.until_keyword.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.condition.format(indentation, config));
+use super::{format_body, format_closing_keyword};
 
-        string
+impl Format for RepeatBlock {
+    fn format(&self, config: &Config) -> Doc {
+        vec![
+            self.repeat_keyword.format(config),
+            format_body(&self.body, config),
+            format_closing_keyword(&self.body, &self.until_keyword, config),
+            Doc::Space,
+            self.condition.format(config),
+        ]
+        .into()
     }
 }
@@ -7,29 +7,33 @@ use luau_parser::types::{CompoundSetExpression, SetExpression};
 
 use crate::{
     config::Config,
-    traits::{Format, FormatWithArgs, Indentation},
+    doc::Doc,
+    formatter::list::ListSeparator,
+    traits::{Format, FormatWithArgs},
 };
 
 impl Format for SetExpression {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let mut string = self.variables.format_with(indentation, config, ", ");
-        string.push(' ');
-        string.push_str(&self.equal.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.values.format_with(indentation, config, ", "));
-
-        string
+    fn format(&self, config: &Config) -> Doc {
+        vec![
+            self.variables.format_with(config, ListSeparator::Space),
+            Doc::Space,
+            self.equal.format(config),
+            Doc::Space,
+            self.values.format_with(config, ListSeparator::Space),
+        ]
+        .into()
     }
 }
 
 impl Format for CompoundSetExpression {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let mut string = self.variable.format(indentation, config);
-        string.push(' ');
-        string.push_str(&self.operation.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.value.format(indentation, config));
-
-        string
+    fn format(&self, config: &Config) -> Doc {
+        vec![
+            self.variable.format(config),
+            Doc::Space,
+            self.operation.format(config),
+            Doc::Space,
+            self.value.format(config),
+        ]
+        .into()
     }
 }
@@ -4,69 +4,56 @@ use luau_parser::types::{Statement, TerminationStatement};
 
 use crate::{
     config::Config,
-    traits::{Format, FormatWithArgs, Indentation},
+    doc::Doc,
+    formatter::list::ListSeparator,
+    traits::{Format, FormatWithArgs},
 };
 
 impl Format for Statement {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         match self {
             Statement::ERROR => unreachable!(),
-            Statement::LocalFunction(local_function) => local_function.format(indentation, config),
-            Statement::LocalAssignment(local_assignment) => {
-                local_assignment.format(indentation, config)
-            }
-            Statement::TypeDefinition(type_definition) => {
-                type_definition.format(indentation, config)
-            }
-            Statement::IfStatement(if_statement) => if_statement.format(indentation, config),
-            Statement::DoBlock(do_block) => do_block.format(indentation, config),
-            Statement::GenericFor(generic_for) => generic_for.format(indentation, config),
-            Statement::NumericalFor(numerical_for) => numerical_for.format(indentation, config),
-            Statement::RepeatBlock(repeat_block) => repeat_block.format(indentation, config),
-            Statement::WhileLoop(while_loop) => while_loop.format(indentation, config),
-            Statement::SetExpression(set_expression) => set_expression.format(indentation, config),
+            Statement::LocalFunction(local_function) => local_function.format(config),
+            Statement::LocalAssignment(local_assignment) => local_assignment.format(config),
+            Statement::TypeDefinition(type_definition) => type_definition.format(config),
+            Statement::IfStatement(if_statement) => if_statement.format(config),
+            Statement::DoBlock(do_block) => do_block.format(config),
+            Statement::GenericFor(generic_for) => generic_for.format(config),
+            Statement::NumericalFor(numerical_for) => numerical_for.format(config),
+            Statement::RepeatBlock(repeat_block) => repeat_block.format(config),
+            Statement::WhileLoop(while_loop) => while_loop.format(config),
+            Statement::SetExpression(set_expression) => set_expression.format(config),
             Statement::CompoundSetExpression(compound_set_expression) => {
-                compound_set_expression.format(indentation, config)
-            }
-            Statement::FunctionCall(function_call) => function_call.format(indentation, config),
-            Statement::GlobalFunction(global_function) => {
-                global_function.format(indentation, config)
+                compound_set_expression.format(config)
             }
-            Statement::TypeFunction(type_function) => type_function.format(indentation, config),
-            Statement::EndOfFile(end_of_file) => end_of_file.format(indentation, config),
+            Statement::FunctionCall(function_call) => function_call.format(config),
+            Statement::GlobalFunction(global_function) => global_function.format(config),
+            Statement::TypeFunction(type_function) => type_function.format(config),
+            Statement::EndOfFile(end_of_file) => end_of_file.format(config),
         }
     }
 }
 
 impl Format for TerminationStatement {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         match self {
             TerminationStatement::Return {
                 return_keyword,
                 expressions: Some(expressions),
-            } => {
-                let string = expressions.format_with(indentation, config, ", ");
-
-                if string.len() > config.column_width {
-                    return_keyword.format(indentation, config)
-                        + " "
-                        + &expressions.format_with(
-                            indentation,
-                            config,
-                            &(",".to_string()
-                                + config.newline_style.as_str()
-                                + &config.indent_style.to_string(indentation, config)),
-                        )
-                } else {
-                    return_keyword.format(indentation, config) + " " + &string
-                }
-            }
+            } => vec![
+                return_keyword.format(config),
+                Doc::Space,
+                Doc::group(Doc::indent_if_break(
+                    expressions.format_with(config, ListSeparator::Line),
+                )),
+            ]
+            .into(),
             TerminationStatement::Break(token)
             | TerminationStatement::Continue(token)
             | TerminationStatement::Return {
                 return_keyword: token,
                 ..
-            } => token.format(indentation, config),
+            } => token.format(config),
         }
     }
 }
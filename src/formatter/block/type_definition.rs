@@ -14,227 +14,188 @@ use luau_parser::types::{
 
 use crate::{
     config::Config,
-    formatter::TokenFormatType,
-    traits::{Expand, ExpandWithArgs, Format, FormatWithArgs, Indentation},
+    doc::Doc,
+    formatter::list::ListSeparator,
+    traits::{Format, FormatWithArgs},
 };
 
-/// Formats the passed [`TypeValue`]. Just used to clean the code a bit.
-fn format_type_value(type_value: &TypeValue, indentation: Indentation, config: &Config) -> String {
-    match type_value {
-        TypeValue::ERROR => unreachable!(),
-        TypeValue::String(token) | TypeValue::Boolean(token) | TypeValue::Nil(token) => {
-            token.format(indentation, config)
-        }
-        TypeValue::Wrap(bracketed) => bracketed.format(indentation + 1, config),
-        TypeValue::Function {
-            generics,
-            parameters,
-            arrow,
-            return_type,
-        } => {
-            let mut string = generics.format_with(indentation, config, ", ");
-
-            handle_parameters_and_returns!(
-                (parameters, + ' ' + Some(arrow), return_type),
-                string,
-                indentation,
-                config
-            );
-
-            string
-        }
-        TypeValue::Basic { base, generics } => {
-            base.format_with(indentation, config, TokenFormatType::Type)
-                + &generics.format_with(indentation, config, ", ")
-        }
-        TypeValue::GenericPack { name, ellipsis } => {
-            name.format_with(indentation, config, TokenFormatType::Type)
-                + &ellipsis.format(indentation, config)
-        }
-        TypeValue::Intersection {
-            left,
-            ampersand: token,
-            right,
-        }
-        | TypeValue::Union {
-            left,
-            pipe: token,
-            right,
-        } => {
-            left.format(indentation, config)
-                + " "
-                + &token.format(indentation, config)
-                + " "
-                + &right.format(indentation, config)
-        }
-        TypeValue::Module {
-            module,
-            dot,
-            name,
-            generics,
-        } => {
-            module.format_with(indentation, config, TokenFormatType::Name)
-                + &dot.format(indentation, config)
-                + &name.format_with(indentation, config, TokenFormatType::Type)
-                + &generics.format_with(indentation, config, ", ")
-        }
-        TypeValue::Optional {
-            base,
-            question_mark,
-        } => base.format(indentation, config) + &question_mark.format(indentation, config),
-        TypeValue::Table(table) => table.format_with(indentation, config, true),
-        TypeValue::Typeof {
-            typeof_token,
-            inner,
-        } => typeof_token.format(indentation, config) + &inner.format(indentation, config),
-        TypeValue::Tuple(bracketed) => bracketed.format_with(indentation + 1, config, ", "),
-        TypeValue::Variadic {
-            ellipsis,
-            type_value,
-        } => ellipsis.format(indentation, config) + &type_value.format(indentation, config),
-        TypeValue::VariadicPack { ellipsis, name } => {
-            ellipsis.format(indentation, config)
-                + &name.format_with(indentation, config, TokenFormatType::Type)
-        }
-    }
-}
+/// Formats the passed [`TypeValue::Union`] or [`TypeValue::Intersection`], and
+/// the ones on its right, as a single chain. If it doesn't fit on the line, each
+/// `|` or `&`, and the type after it, is put on its own line.
+fn format_type_chain(type_value: &TypeValue, config: &Config) -> Doc {
+    let mut docs = Vec::new();
+    let mut current = type_value;
 
-impl Format for TypeValue {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let string = format_type_value(self, indentation, config);
-
-        if string.len() > config.column_width {
-            self.expand(indentation, config)
-        } else {
-            string
-        }
-    }
-}
-
-impl Expand for TypeValue {
-    fn expand(&self, indentation: Indentation, config: &Config) -> String {
-        match self {
-            Self::Wrap(bracketed) => bracketed.expand(indentation + 1, config),
-            Self::Intersection {
+    loop {
+        match current {
+            TypeValue::Intersection {
                 left,
                 ampersand: token,
                 right,
             }
-            | Self::Union {
+            | TypeValue::Union {
                 left,
                 pipe: token,
                 right,
             } => {
-                let right = if matches!(
-                    &**right,
-                    TypeValue::Union { .. } | TypeValue::Intersection { .. }
-                ) {
-                    right.expand(indentation, config)
-                } else {
-                    right.format(indentation, config)
-                };
-
-                left.format(indentation, config)
-                    + config.newline_style.as_str()
-                    + &config.indent_style.to_string(indentation + 1, config)
-                    + &token.format(indentation, config)
-                    + " "
-                    + &right
+                docs.push(left.format(config));
+                docs.push(Doc::Line);
+                docs.push(token.format(config));
+                docs.push(Doc::Space);
+                current = right;
+            }
+            _ => {
+                docs.push(current.format(config));
+                break;
+            }
+        }
+    }
+
+    let rest = docs.split_off(1);
+
+    Doc::group(vec![docs.remove(0), Doc::indent_if_break(rest.into())].into())
+}
+
+impl Format for TypeValue {
+    fn format(&self, config: &Config) -> Doc {
+        match self {
+            TypeValue::ERROR => unreachable!(),
+            TypeValue::String(token) | TypeValue::Boolean(token) | TypeValue::Nil(token) => {
+                token.format(config)
+            }
+            TypeValue::Wrap(bracketed) => bracketed.format(config),
+            TypeValue::Function {
+                generics,
+                parameters,
+                arrow,
+                return_type,
+            } => {
+                let mut docs = vec![generics.format_with(config, ListSeparator::Line)];
+
+                handle_parameters_and_returns!(
+                    (parameters, + ' ' + Some(arrow), return_type),
+                    docs,
+                    config
+                );
+
+                docs.into()
+            }
+            TypeValue::Basic { base, generics } => vec![
+                base.format(config),
+                generics.format_with(config, ListSeparator::Line),
+            ]
+            .into(),
+            TypeValue::GenericPack { name, ellipsis } => vec![
+                name.format(config),
+                ellipsis.format(config),
+            ]
+            .into(),
+            TypeValue::Intersection { .. } | TypeValue::Union { .. } => {
+                format_type_chain(self, config)
             }
-            Self::Table(table) => table.format_with(indentation, config, true),
-            Self::Typeof {
+            TypeValue::Module {
+                module,
+                dot,
+                name,
+                generics,
+            } => vec![
+                module.format(config),
+                dot.format(config),
+                name.format(config),
+                generics.format_with(config, ListSeparator::Line),
+            ]
+            .into(),
+            TypeValue::Optional {
+                base,
+                question_mark,
+            } => vec![base.format(config), question_mark.format(config)].into(),
+            TypeValue::Table(table) => table.format_with(config, true),
+            TypeValue::Typeof {
                 typeof_token,
                 inner,
-            } => typeof_token.format(indentation, config) + &inner.expand(indentation + 1, config),
-            Self::Tuple(bracketed) => bracketed.expand_with(
-                indentation + 1,
-                config,
-                &(",".to_string()
-                    + &config.newline_style.to_string()
-                    + &config.indent_style.to_string(indentation + 1, config)),
-            ),
-            Self::Variadic {
+            } => vec![typeof_token.format(config), inner.format(config)].into(),
+            TypeValue::Tuple(bracketed) => bracketed.format_with(config, ListSeparator::Line),
+            TypeValue::Variadic {
                 ellipsis,
                 type_value,
-            } => ellipsis.format(indentation, config) + &type_value.format(indentation, config),
-            Self::VariadicPack { ellipsis, name } => {
-                ellipsis.format(indentation, config)
-                    + &name.format_with(indentation, config, TokenFormatType::Type)
-            }
-            _ => format_type_value(self, indentation, config),
+            } => vec![ellipsis.format(config), type_value.format(config)].into(),
+            TypeValue::VariadicPack { ellipsis, name } => vec![
+                ellipsis.format(config),
+                name.format(config),
+            ]
+            .into(),
         }
     }
 }
 
 impl Format for ParameterTypeName {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         match self {
-            Self::Normal(name) => name.format(indentation, config),
-            Self::Type(type_value) => type_value.format(indentation, config),
+            Self::Normal(name) => name.format(config),
+            Self::Type(type_value) => type_value.format(config),
         }
     }
 }
 
 impl Format for TypeDefinition {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let mut string = if self.export_keyword.is_some() {
-            let mut string = self.export_keyword.format(indentation, config);
-            string.push(' ');
-            string
-        } else {
-            String::new()
-        };
-
-        string.push_str(&self.type_keyword.format(indentation, config));
-        string.push(' ');
-        string.push_str(
-            &self
-                .type_name
-                .format_with(indentation, config, TokenFormatType::Type),
-        );
-        string.push_str(&self.generics.format_with(indentation, config, ", "));
-        string.push(' ');
-        string.push_str(&self.equal_sign.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.type_value.format(indentation, config));
-
-        string
+    fn format(&self, config: &Config) -> Doc {
+        let mut docs = Vec::new();
+
+        if self.export_keyword.is_some() {
+            docs.push(self.export_keyword.format(config));
+            docs.push(Doc::Space);
+        }
+
+        docs.push(self.type_keyword.format(config));
+        docs.push(Doc::Space);
+        docs.push(self.type_name.format(config));
+        docs.push(self.generics.format_with(config, ListSeparator::Line));
+        docs.push(Doc::Space);
+        docs.push(self.equal_sign.format(config));
+        docs.push(Doc::Space);
+        docs.push(self.type_value.format(config));
+
+        docs.into()
     }
 }
 
 impl Format for GenericParameterInfo {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         match self {
             Self::ERROR => unreachable!(),
-            Self::Name(token) => token.format_with(indentation, config, TokenFormatType::Type),
-            Self::Pack { name, ellipsis } => {
-                name.format_with(indentation, config, TokenFormatType::Type)
-                    + &ellipsis.format(indentation, config)
-            }
+            Self::Name(token) => token.format(config),
+            Self::Pack { name, ellipsis } => vec![
+                name.format(config),
+                ellipsis.format(config),
+            ]
+            .into(),
         }
     }
 }
 
 impl Format for GenericDeclarationParameter {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         if let Some(default) = self.default.as_ref() {
-            self.parameter.format(indentation, config)
-                + " "
-                + &self.equal.format(indentation, config)
-                + " "
-                + &default.format(indentation, config)
+            vec![
+                self.parameter.format(config),
+                Doc::Space,
+                self.equal.format(config),
+                Doc::Space,
+                default.format(config),
+            ]
+            .into()
         } else {
-            self.parameter.format(indentation, config)
+            self.parameter.format(config)
         }
     }
 }
 
 impl Format for GenericParameterInfoDefault {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         match self {
             Self::ERROR => unreachable!(),
-            Self::Name(token) => token.format_with(indentation, config, TokenFormatType::Type),
-            Self::Pack(type_value) => type_value.format(indentation, config),
+            Self::Name(token) => token.format(config),
+            Self::Pack(type_value) => type_value.format(config),
         }
     }
 }
@@ -2,19 +2,17 @@
 
 use luau_parser::types::WhileLoop;
 
-use crate::{
-    config::Config,
-    traits::{Format, Indentation},
-};
+use crate::{config::Config, doc::Doc, traits::Format};
 
 impl Format for WhileLoop {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let mut string = self.while_keyword.format(indentation, config);
-        string.push(' ');
-        string.push_str(&self.condition.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.do_block.format(indentation, config));
-
-        string
+    fn format(&self, config: &Config) -> Doc {
+        vec![
+            self.while_keyword.format(config),
+            Doc::Space,
+            self.condition.format(config),
+            Doc::Space,
+            self.do_block.format(config),
+        ]
+        .into()
     }
 }
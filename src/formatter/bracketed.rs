@@ -1,103 +1,70 @@
 //! All `impl` blocks for [`Bracketed`].
 
-use luau_parser::types::Bracketed;
+use luau_parser::{prelude::Token, types::Bracketed};
 
 use crate::{
     config::Config,
-    traits::{Expand, ExpandWithArgs, Format, FormatWithArgs, Indentation},
+    doc::{Doc, trim_hard_lines},
+    formatter::{format_without_leading_comments, trivia::format_own_line_comments},
+    traits::{Format, FormatWithArgs},
 };
 
-/// Actual implementation for [`Bracketed<T>`] formatting.
-macro_rules! format {
-    (
-        $self: ident,
-        $indentation: ident,
-        $config: ident,
-        $item: expr;
-    ) => {{
-        let mut string = $self.opening_bracket.format($indentation, $config);
-        string.push_str(&$item);
-        string.push_str(&$self.closing_bracket.format($indentation, $config));
+use super::trivia::split_at_line_break;
 
-        string
-    }};
-}
+/// Puts the passed formatted item between the passed brackets, in a group that
+/// puts it on its own lines, indented, if it doesn't fit on the current one. The
+/// comments on their own lines before the closing bracket are put after the
+/// item, indented like it.
+pub(crate) fn format_bracketed(
+    opening_bracket: &Token,
+    mut item: Doc,
+    closing_bracket: &Token,
+    config: &Config,
+) -> Doc {
+    item.trim_trailing_space();
 
-impl<T: Format> Format for Bracketed<T> {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        format!(
-            self,
-            indentation,
-            config,
-            self.item.format(indentation, config);
-        )
-    }
-}
+    let mut comments =
+        format_own_line_comments(split_at_line_break(&closing_bracket.leading_trivia).1, config);
+    trim_hard_lines(&mut comments);
 
-impl<A, T: FormatWithArgs<A>> FormatWithArgs<A> for Bracketed<T> {
-    fn format_with(&self, indentation: Indentation, config: &Config, args: A) -> String {
-        format!(
-            self,
-            indentation,
-            config,
-            self.item.format_with(indentation, config, args);
-        )
+    let mut contents = Vec::new();
+    if !item.is_empty() {
+        contents.push(Doc::SoftLine);
+        contents.push(item);
+    }
+    if !comments.is_empty() {
+        contents.push(Doc::SoftLine);
+        contents.extend(comments);
     }
-}
-
-/// Actual implementation for [`Bracketed<T>`] expanding.
-macro_rules! expand {
-    (
-        $self: ident,
-        $indentation: ident,
-        $config: ident,
-        let $item_name:ident = $item:expr;
-    ) => {{
-        let $item_name = $item;
-
-        if $item_name.is_empty() {
-            let mut string = $self.opening_bracket.format($indentation, $config);
-            string.push_str(&$item_name);
-            string.push_str(&$self.closing_bracket.format($indentation, $config));
-
-            string
-        } else {
-            let mut string = $self.opening_bracket.format($indentation, $config);
 
-            string.push_str(
-                &($config.newline_style.to_string()
-                    + &$config.indent_style.to_string($indentation, $config)),
-            );
-            string.push_str(&$item_name);
-            string.push_str(
-                &($config.newline_style.to_string()
-                    + &$config.indent_style.to_string($indentation.saturating_sub(1), $config)),
-            );
-            string.push_str(&$self.closing_bracket.format($indentation, $config));
+    let mut docs = vec![opening_bracket.format(config)];
+    if !contents.is_empty() {
+        docs.push(Doc::indent_if_break(contents.into()));
+        docs.push(Doc::SoftLine);
+    }
+    docs.push(format_without_leading_comments(closing_bracket, config));
 
-            string
-        }
-    }};
+    Doc::group(docs.into())
 }
 
-impl<A, T: FormatWithArgs<A>> ExpandWithArgs<A> for Bracketed<T> {
-    fn expand_with(&self, indentation: Indentation, config: &Config, args: A) -> String {
-        expand!(
-            self,
-            indentation,
+impl<T: Format> Format for Bracketed<T> {
+    fn format(&self, config: &Config) -> Doc {
+        format_bracketed(
+            &self.opening_bracket,
+            self.item.format(config),
+            &self.closing_bracket,
             config,
-            let item = self.item.format_with(indentation, config, args);
         )
     }
 }
 
-impl<T: Expand> Expand for Bracketed<T> {
-    fn expand(&self, indentation: Indentation, config: &Config) -> String {
-        expand!(
-            self,
-            indentation,
+impl<A, T: FormatWithArgs<A>> FormatWithArgs<A> for Bracketed<T> {
+    fn format_with(&self, config: &Config, args: A) -> Doc {
+        format_bracketed(
+            &self.opening_bracket,
+            self.item.format_with(config, args),
+            &self.closing_bracket,
             config,
-            let item = self.item.expand(indentation, config);
         )
     }
 }
@@ -6,30 +6,51 @@
 //! * [`FunctionArgument`]
 //! * [`Closure`]
 
-use luau_parser::types::{
-    Closure, Expression, FunctionArgument, FunctionArguments, FunctionCall, FunctionCallInvoked,
+use luau_parser::{
+    prelude::{Literal, LuauString, TokenType, Trivia},
+    types::{
+        Closure, Expression, FunctionArgument, FunctionArguments, FunctionCall,
+        FunctionCallInvoked,
+    },
 };
+use std::mem;
 
 use crate::{
     config::{Config, FunctionParenthesis},
-    formatter::TokenFormatType,
-    traits::{Expand, ExpandWithArgs, Format, FormatWithArgs, Indentation},
+    doc::Doc,
+    formatter::{
+        format_string_expression, is_concatenated,
+        list::ListSeparator,
+        trivia::TriviaFormattingType,
+    },
+    traits::{Format, FormatWithArgs},
 };
 
 impl Format for FunctionCallInvoked {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         match self {
-            Self::Function(prefix_exp) => prefix_exp.format(indentation, config),
+            Self::Function(prefix_exp) => prefix_exp.format(config),
             Self::TableMethod {
                 table,
                 colon,
                 method,
             } => {
-                let mut string = table.format(indentation, config);
-                string.push_str(&colon.format(indentation, config));
-                string.push_str(&method.format_with(indentation, config, TokenFormatType::Method));
-
-                string
+                // The method is put on the next line if it doesn't fit, which
+                // splits chained method calls.
+                Doc::group(
+                    vec![
+                        table.format(config),
+                        Doc::indent_if_break(
+                            vec![
+                                Doc::SoftLine,
+                                colon.format(config),
+                                method.format(config),
+                            ]
+                            .into(),
+                        ),
+                    ]
+                    .into(),
+                )
             }
         }
     }
@@ -37,126 +58,134 @@ impl Format for FunctionCallInvoked {
 
 impl Format for FunctionCall {
     #[inline]
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        self.invoked.format(indentation, config) + &self.arguments.format(indentation, config)
+    fn format(&self, config: &Config) -> Doc {
+        vec![self.invoked.format(config), self.arguments.format(config)].into()
     }
 }
 
-/// Whether or not the passed [`FunctionArgument`] is a table or a string.
+/// Whether or not the passed [`FunctionArgument`] is a table or a string that
+/// can be passed to a function without parentheses, as `(is_string, is_table)`.
+/// Strings split into several ones joined with `..` can't.
 #[inline]
-fn is_table_or_string(function_argument: &FunctionArgument) -> bool {
-    matches!(
-        function_argument,
-        FunctionArgument::Expression(expression) if matches!(&**expression, Expression::Table(_) | Expression::String(_))
-    )
+fn is_string_or_table(function_argument: &FunctionArgument, config: &Config) -> (bool, bool) {
+    let FunctionArgument::Expression(expression) = function_argument else {
+        return (false, false);
+    };
+
+    match &**expression {
+        Expression::String(token) => (
+            !matches!(
+                token.token_type,
+                TokenType::Literal(Literal::String(LuauString::Backticks(_)))
+            ) && !is_concatenated(token, config),
+            false,
+        ),
+        Expression::Table(_) => (false, true),
+        _ => (false, false),
+    }
+}
+
+/// Whether or not the passed trivia has any comment.
+#[inline]
+fn has_comments(trivia: &[Trivia]) -> bool {
+    trivia.iter().any(|trivia| matches!(trivia, Trivia::Comment(_)))
+}
+
+/// Wraps the passed [`FunctionArguments`], a table or a string without
+/// parentheses, in parentheses, keeping the comments after it outside them.
+fn add_parentheses(function_arguments: &FunctionArguments, config: &Config) -> Doc {
+    let mut argument = function_arguments.clone();
+    let last_token = match &mut argument {
+        FunctionArguments::String(token) => token,
+        FunctionArguments::Table(table) => &mut table.0.closing_bracket,
+        FunctionArguments::List(_) => unreachable!(),
+    };
+    let trailing_trivia = mem::take(&mut last_token.trailing_trivia);
+
+    let argument = match &argument {
+        FunctionArguments::String(token) => format_string_expression(token, false, config),
+        FunctionArguments::Table(table) => table.format_with(config, false),
+        FunctionArguments::List(_) => unreachable!(),
+    };
+
+    vec![
+        Doc::group(
+            vec![
+                Doc::text("("),
+                Doc::indent_if_break(vec![Doc::SoftLine, argument].into()),
+                Doc::SoftLine,
+                Doc::text(")"),
+            ]
+            .into(),
+        ),
+        trailing_trivia.format_with(config, TriviaFormattingType::TrailingComments),
+    ]
+    .into()
 }
 
 impl Format for FunctionArguments {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let (is_string, is_table, string) = match self {
-            Self::String(token) => (true, false, token.format(indentation, config)),
-            Self::Table(table) => (false, true, table.format_with(indentation, config, false)),
-            Self::List(bracketed)
-                if config.function_parenthesis == FunctionParenthesis::RemoveWhenPossible
-                    && bracketed.len() == 1
-                    && is_table_or_string(&bracketed[0]) =>
-            {
+    fn format(&self, config: &Config) -> Doc {
+        let (is_string, is_table, has_parentheses, doc) = match self {
+            Self::String(token) => (true, false, false, token.format(config)),
+            Self::Table(table) => (false, true, false, table.format_with(config, false)),
+            Self::List(bracketed) => {
+                let (is_string, is_table) = match &bracketed[..] {
+                    [argument] => is_string_or_table(argument, config),
+                    _ => (false, false),
+                };
+                // Comments in the parentheses would have nowhere to go.
+                if (!is_string && !is_table)
+                    || has_comments(&bracketed.opening_bracket.trailing_trivia)
+                    || has_comments(&bracketed.closing_bracket.leading_trivia)
+                {
+                    return bracketed.format_with(config, ListSeparator::Line);
+                }
+
                 (
-                    // The booleans aren't used if this match arm is reached,
-                    // so we don't have to worry about their values.
-                    false,
-                    false,
-                    " ".to_string() + &bracketed.item.format_with(indentation, config, ", "),
+                    is_string,
+                    is_table,
+                    true,
+                    bracketed.item.format_with(config, ListSeparator::Line),
                 )
             }
-            Self::List(bracketed) => (
-                false,
-                false,
-                bracketed.format_with(indentation, config, ", "),
-            ),
         };
 
-        match config.function_parenthesis {
-            FunctionParenthesis::Always if is_string || is_table => "(".to_string() + &string + ")",
-            FunctionParenthesis::Keep if is_string || is_table => " ".to_string() + &string,
-            FunctionParenthesis::RemoveForStrings if is_table => "(".to_string() + &string + ")",
-            FunctionParenthesis::RemoveForTables if is_string => "(".to_string() + &string + ")",
-            FunctionParenthesis::RemoveForStrings | FunctionParenthesis::RemoveForTables => {
-                " ".to_string() + &string
-            }
-            FunctionParenthesis::RemoveWhenPossible if is_string || is_table => {
-                " ".to_string() + &string
-            }
-            _ => string,
+        let remove_parentheses = match config.function_parenthesis {
+            FunctionParenthesis::Always => false,
+            FunctionParenthesis::Keep => !has_parentheses,
+            FunctionParenthesis::RemoveForStrings => is_string,
+            FunctionParenthesis::RemoveForTables => is_table,
+            FunctionParenthesis::RemoveWhenPossible => true,
+        };
+
+        match (remove_parentheses, self) {
+            (true, Self::List(bracketed)) => vec![
+                Doc::Space,
+                doc,
+                bracketed
+                    .closing_bracket
+                    .trailing_trivia
+                    .format_with(config, TriviaFormattingType::TrailingComments),
+            ]
+            .into(),
+            (true, _) => vec![Doc::Space, doc].into(),
+            (false, Self::List(bracketed)) => bracketed.format_with(config, ListSeparator::Line),
+            (false, _) => add_parentheses(self, config),
         }
     }
 }
 
 impl Format for FunctionArgument {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         match self {
-            Self::Expression(expression) => expression.format(indentation, config),
-            Self::VariadicValues(token) => token.format(indentation, config),
+            Self::Expression(expression) => expression.format(config),
+            Self::VariadicValues(token) => token.format(config),
         }
     }
 }
 
 impl Format for Closure {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        format_function!(self, indentation, config,)
-    }
-}
-
-impl Expand for FunctionCallInvoked {
-    fn expand(&self, indentation: Indentation, config: &Config) -> String {
-        match self {
-            Self::Function(prefix_exp) => prefix_exp.expand(indentation, config),
-            Self::TableMethod {
-                table,
-                colon,
-                method,
-            } => {
-                let mut string = table.expand(indentation, config);
-                string.push_str(
-                    &(config.newline_style.to_string()
-                        + &config.indent_style.to_string(indentation + 1, config)),
-                );
-                string.push_str(&colon.format(indentation, config));
-                string.push_str(&method.format_with(indentation, config, TokenFormatType::Method));
-
-                string
-            }
-        }
-    }
-}
-
-impl Expand for FunctionCall {
-    fn expand(&self, indentation: Indentation, config: &Config) -> String {
-        let string = self.invoked.format(indentation, config);
-        let arguments = self.arguments.format(indentation, config);
-
-        if string.len() > config.column_width {
-            self.invoked.expand(indentation, config) + &arguments
-        } else if arguments.find('\n') != arguments.rfind('\n') {
-            // This is most likely a closure, no need to expand.
-            string + &arguments
-        } else {
-            string + &self.arguments.expand(indentation, config)
-        }
-    }
-}
-
-impl Expand for FunctionArguments {
-    fn expand(&self, indentation: Indentation, config: &Config) -> String {
-        match self {
-            Self::List(bracketed) => bracketed.expand_with(
-                indentation + 1,
-                config,
-                &(",".to_string()
-                    + &config.newline_style.to_string()
-                    + &config.indent_style.to_string(indentation + 1, config)),
-            ),
-            _ => self.format(indentation, config),
-        }
+    fn format(&self, config: &Config) -> Doc {
+        format_function!(self, config,)
     }
 }
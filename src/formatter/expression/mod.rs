@@ -5,282 +5,287 @@ mod table;
 mod var;
 
 use luau_parser::{
-    prelude::{Operator, TokenType},
+    prelude::{Operator, Token, TokenType},
     types::{ElseIfExpression, Expression, IfExpression, PrefixExp},
 };
 
 use crate::{
-    config::Config,
-    traits::{Expand, Format, FormatWithArgs, Indentation},
+    config::{Config, StringWrap},
+    doc::Doc,
+    formatter::{format_string_expression, is_concatenated, trivia::split_at_line_break},
+    traits::{Format, FormatWithArgs},
 };
 
 impl Format for PrefixExp {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         match self {
-            Self::Var(var) => var.format(indentation, config),
-            Self::FunctionCall(function_call) => function_call.format(indentation, config),
-            Self::ExpressionWrap(bracketed) => bracketed.format(indentation + 1, config),
+            Self::Var(var) => var.format(config),
+            Self::FunctionCall(function_call) => function_call.format(config),
+            Self::ExpressionWrap(bracketed) => bracketed.format(config),
         }
     }
 }
 
-impl Expand for PrefixExp {
-    fn expand(&self, indentation: Indentation, config: &Config) -> String {
-        match self {
-            Self::Var(var) => var.expand(indentation, config),
-            Self::FunctionCall(function_call) => function_call.expand(indentation, config),
-            _ => self.format(indentation, config),
-        }
+/// Formats the passed [`Expression`], used as the operand of an operator.
+fn format_operand(expression: &Expression, config: &Config) -> Doc {
+    match expression {
+        Expression::String(token) => format_string_expression(token, true, config),
+        expression => expression.format(config),
     }
 }
 
-impl Format for Expression {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let string = match self {
-            Self::ERROR => unreachable!(),
-            Self::Nil(token) | Self::Boolean(token) | Self::Number(token) | Self::String(token) => {
-                token.format(indentation + 1, config)
-            }
-            Self::Closure(closure) => closure.format(indentation, config),
-            Self::FunctionCall(function_call) => function_call.format(indentation, config),
-            Self::ExpressionWrap(bracketed) => bracketed.format(indentation + 1, config),
-            Self::Var(var) => var.format(indentation, config),
-            Self::Table(table) => table.format_with(indentation, config, false),
-            Self::UnaryExpression {
-                operator,
-                expression,
-            } => {
-                if matches!(operator.token_type, TokenType::Operator(Operator::Not)) {
-                    operator.format(indentation, config)
-                        + " "
-                        + &expression.format(indentation, config)
-                } else {
-                    operator.format(indentation, config) + &expression.format(indentation, config)
-                }
-            }
-            Self::BinaryExpression {
-                left,
-                operator,
-                right,
-            } => {
-                if matches!(
-                    operator.token_type,
-                    TokenType::Operator(Operator::Exponentiation)
-                ) {
-                    left.format(indentation, config)
-                        + &operator.format(indentation, config)
-                        + &right.format(indentation, config)
-                } else {
-                    left.format(indentation, config)
-                        + " "
-                        + &operator.format(indentation, config)
-                        + " "
-                        + &right.format(indentation, config)
-                }
-            }
-            Self::TypeCast {
-                expression,
-                operator,
-                cast_to,
-            } => {
-                expression.format(indentation, config)
-                    + " "
-                    + &operator.format(indentation, config)
-                    + " "
-                    + &cast_to.format(indentation, config)
-            }
-            Self::IfExpression(if_expression) => if_expression.format(indentation, config),
-        };
+/// Whether or not the passed operator is `and` or `or`, which have the lowest
+/// precedence.
+#[inline]
+fn is_logical_operator(operator: &Token) -> bool {
+    matches!(
+        operator.token_type,
+        TokenType::Operator(Operator::And | Operator::Or)
+    )
+}
 
-        if string.len() > config.column_width {
-            // This simple gives priority when expanding. If we have
-            // expr ~= expr and expr ~= expr
-            // without this priority thing, it'll become something like:
-            //
-            // expr
-            //     ~= expr and expr ~= expr
-            //
-            // but with it, it becomes
-            //
-            // expr ~= expr
-            //     and expr ~= expr
-            //
-            // This also handles cases of super long strings and makes them not look
-            // awkward.
+/// Whether or not the passed operator joins two strings that are on different
+/// lines, which they're kept on if [`Config::string_wrap`] is
+/// [`StringWrap::Concatenation`], as they're usually a long string split there.
+fn is_split_string(
+    operator: &Token,
+    is_string: bool,
+    is_operand_string: bool,
+    config: &Config,
+) -> bool {
+    config.string_wrap() == StringWrap::Concatenation
+        && is_string
+        && is_operand_string
+        && matches!(
+            operator.token_type,
+            TokenType::Operator(Operator::Concatenation)
+        )
+        && !split_at_line_break(&operator.leading_trivia).1.is_empty()
+}
 
-            match self {
-                Self::BinaryExpression {
-                    left,
-                    operator,
-                    right,
-                } => {
-                    if matches!(&**right, Self::String(_)) | matches!(&**left, Self::String(_)) {
-                        // This means it's just a super long string.
-                        return string;
-                    }
+/// Joins the passed operands with the operators between them, in a group that
+/// puts each operator, and the operand after it, on its own line if it doesn't
+/// fit on the current one. These lines are indented if `indent` is set, or if
+/// they split a string. Operators next to a string aren't put on their own lines, as it's just a
+/// super long string, unless it's split there, nor are exponentiations.
+fn format_operator_chain(
+    operands: Vec<(Doc, bool)>,
+    operators: &[&Token],
+    indent: bool,
+    config: &Config,
+) -> Doc {
+    let mut operands = operands.into_iter();
+    let Some((first, mut is_string)) = operands.next() else {
+        return Doc::Nil;
+    };
 
-                    match &**right {
-                        Self::BinaryExpression {
-                            operator: right_operator,
-                            ..
-                        } => match right_operator.token_type {
-                            TokenType::Operator(Operator::And | Operator::Or) => {
-                                left.format(indentation, config)
-                                    + " "
-                                    + &operator.format(indentation, config)
-                                    + " "
-                                    + &right.expand(indentation, config)
-                            }
-                            _ => self.expand(indentation, config),
-                        },
-                        _ => self.expand(indentation, config),
-                    }
-                }
-                _ => self.expand(indentation, config),
-            }
+    let mut docs = Vec::new();
+    let mut breaks = false;
+    for (operator, (operand, is_operand_string)) in operators.iter().zip(operands) {
+        if matches!(
+            operator.token_type,
+            TokenType::Operator(Operator::Exponentiation)
+        ) {
+            docs.push(operator.format(config));
         } else {
-            string
+            docs.push(if is_split_string(operator, is_string, is_operand_string, config) {
+                breaks = true;
+                Doc::HardLine
+            } else if is_string || is_operand_string {
+                Doc::Space
+            } else {
+                Doc::Line
+            });
+            docs.push(operator.format(config));
+            docs.push(Doc::Space);
         }
+
+        docs.push(operand);
+        is_string = is_operand_string;
     }
+
+    // Split strings break the chain without the lines around it, so it has to
+    // be indented even where it would otherwise be on its own lines.
+    let docs = if indent || breaks {
+        Doc::indent_if_break(docs.into())
+    } else {
+        docs.into()
+    };
+
+    Doc::group_with(vec![first, docs].into(), breaks)
 }
 
-impl Expand for Expression {
-    fn expand(&self, indentation: Indentation, config: &Config) -> String {
+/// Formats the passed [`Expression::BinaryExpression`], and the binary
+/// expressions on its right, as a single chain. It's broken at `and` and `or`
+/// first, so
+///
+/// ```luau
+/// expr ~= expr and expr ~= expr
+/// ```
+///
+/// becomes
+///
+/// ```luau
+/// expr ~= expr
+///     and expr ~= expr
+/// ```
+///
+/// instead of breaking at the first operator. `indent` is whether the lines
+/// after the first one are indented, they aren't when the chain is already on
+/// its own lines, like the condition of an `if` statement.
+pub(crate) fn format_binary_expression(
+    expression: &Expression,
+    indent: bool,
+    config: &Config,
+) -> Doc {
+    let mut operands = Vec::new();
+    let mut operators = Vec::new();
+    let mut current = expression;
+
+    while let Expression::BinaryExpression {
+        left,
+        operator,
+        right,
+    } = current
+    {
+        operands.push(&**left);
+        operators.push(operator);
+        current = right;
+    }
+    operands.push(current);
+
+    let format_operand = |operand: &Expression| {
+        (
+            format_operand(operand, config),
+            // It's between parentheses if it's split into several strings.
+            matches!(operand, Expression::String(token) if !is_concatenated(token, config)),
+        )
+    };
+
+    if !operators.iter().any(|operator| is_logical_operator(operator))
+        || operators.iter().all(|operator| is_logical_operator(operator))
+    {
+        return format_operator_chain(
+            operands.into_iter().map(format_operand).collect(),
+            &operators,
+            indent,
+            config,
+        );
+    }
+
+    let mut segments = Vec::new();
+    let mut logical_operators = Vec::new();
+    let mut start = 0;
+
+    for (i, operator) in operators.iter().enumerate() {
+        if is_logical_operator(operator) {
+            segments.push(format_operator_chain(
+                operands[start..=i].iter().map(|operand| format_operand(operand)).collect(),
+                &operators[start..i],
+                true,
+                config,
+            ));
+            logical_operators.push(*operator);
+            start = i + 1;
+        }
+    }
+    segments.push(format_operator_chain(
+        operands[start..].iter().map(|operand| format_operand(operand)).collect(),
+        &operators[start..],
+        true,
+        config,
+    ));
+
+    format_operator_chain(
+        segments.into_iter().map(|segment| (segment, false)).collect(),
+        &logical_operators,
+        indent,
+        config,
+    )
+}
+
+impl Format for Expression {
+    fn format(&self, config: &Config) -> Doc {
         match self {
             Self::ERROR => unreachable!(),
-            Self::Nil(token) | Self::Boolean(token) | Self::Number(token) | Self::String(token) => {
-                token.format(indentation + 1, config)
-            }
-            Self::Closure(closure) => closure.format(indentation, config),
-            Self::FunctionCall(function_call) => function_call.expand(indentation, config),
-            Self::ExpressionWrap(bracketed) => bracketed.expand(indentation + 1, config),
-            Self::Var(var) => var.expand(indentation, config),
-            Self::Table(table) => table.format_with(indentation, config, false),
+            Self::Nil(token) | Self::Boolean(token) | Self::Number(token) => token.format(config),
+            Self::String(token) => format_string_expression(token, false, config),
+            Self::Closure(closure) => closure.format(config),
+            Self::FunctionCall(function_call) => function_call.format(config),
+            Self::ExpressionWrap(bracketed) => bracketed.format(config),
+            Self::Var(var) => var.format(config),
+            Self::Table(table) => table.format_with(config, false),
             Self::UnaryExpression {
                 operator,
                 expression,
             } => {
                 if matches!(operator.token_type, TokenType::Operator(Operator::Not)) {
-                    operator.format(indentation, config)
-                        + " "
-                        + &expression.format(indentation, config)
-                } else {
-                    operator.format(indentation, config) + &expression.format(indentation, config)
-                }
-            }
-            Self::BinaryExpression {
-                left,
-                operator,
-                right,
-            } => {
-                if matches!(
-                    operator.token_type,
-                    TokenType::Operator(Operator::Exponentiation)
-                ) {
-                    left.format(indentation, config)
-                        + &operator.format(indentation, config)
-                        + &right.format(indentation, config)
+                    vec![operator.format(config), Doc::Space, format_operand(expression, config)]
+                        .into()
                 } else {
-                    left.format(indentation, config)
-                        + config.newline_style.as_str()
-                        + &config.indent_style.to_string(indentation + 1, config)
-                        + &operator.format(indentation, config)
-                        + " "
-                        + &right.format(indentation, config)
+                    vec![operator.format(config), format_operand(expression, config)].into()
                 }
             }
+            Self::BinaryExpression { .. } => format_binary_expression(self, true, config),
             Self::TypeCast {
                 expression,
                 operator,
                 cast_to,
-            } => {
-                expression.expand(indentation, config)
-                    + " "
-                    + &operator.format(indentation, config)
-                    + " "
-                    + &cast_to.expand(indentation, config)
-            }
-            Self::IfExpression(if_expression) => if_expression.expand(indentation, config),
+            } => vec![
+                format_operand(expression, config),
+                Doc::Space,
+                operator.format(config),
+                Doc::Space,
+                cast_to.format(config),
+            ]
+            .into(),
+            Self::IfExpression(if_expression) => if_expression.format(config),
         }
     }
 }
 
 impl Format for IfExpression {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let mut string = self.if_keyword.format(indentation, config);
-        string.push(' ');
-        string.push_str(&self.condition.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.then_keyword.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.if_expression.format(indentation, config));
-
-        for else_if in self.else_if_expressions.iter() {
-            string.push_str(&else_if.format(indentation, config));
-        }
-
-        string.push(' ');
-        string.push_str(&self.else_keyword.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.else_expression.format(indentation, config));
-
-        string
-    }
-}
-impl Expand for IfExpression {
-    fn expand(&self, indentation: Indentation, config: &Config) -> String {
-        let newline_separator = config.newline_style.to_string()
-            + &config.indent_style.to_string(indentation + 1, config);
-
-        let mut string = self.if_keyword.format(indentation, config);
-        string.push(' ');
-        string.push_str(&self.condition.format(indentation, config));
-        string.push_str(&newline_separator);
-        string.push_str(&self.then_keyword.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.if_expression.format(indentation, config));
+    fn format(&self, config: &Config) -> Doc {
+        let mut docs = vec![
+            Doc::Line,
+            self.then_keyword.format(config),
+            Doc::Space,
+            self.if_expression.format(config),
+        ];
 
         for else_if in self.else_if_expressions.iter() {
-            string.push_str(&else_if.expand(indentation, config));
+            docs.push(else_if.format(config));
         }
 
-        string.push_str(&newline_separator);
-        string.push_str(&self.else_keyword.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.else_expression.format(indentation, config));
+        docs.push(Doc::Line);
+        docs.push(self.else_keyword.format(config));
+        docs.push(Doc::Space);
+        docs.push(self.else_expression.format(config));
 
-        string
+        Doc::group(
+            vec![
+                self.if_keyword.format(config),
+                Doc::Space,
+                self.condition.format(config),
+                Doc::indent_if_break(docs.into()),
+            ]
+            .into(),
+        )
     }
 }
 
 impl Format for ElseIfExpression {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let mut string = " ".to_string();
-        string.push_str(&self.else_if_keyword.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.condition.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.then_keyword.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.expression.format(indentation, config));
-
-        string
-    }
-}
-impl Expand for ElseIfExpression {
-    fn expand(&self, indentation: Indentation, config: &Config) -> String {
-        let newline_separator = config.newline_style.to_string()
-            + &config.indent_style.to_string(indentation + 1, config);
-
-        let mut string = " ".to_string();
-        string.push_str(&self.else_if_keyword.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.condition.format(indentation, config));
-        string.push_str(&newline_separator);
-        string.push_str(&self.then_keyword.format(indentation, config));
-        string.push(' ');
-        string.push_str(&self.expression.format(indentation, config));
-
-        string
+    fn format(&self, config: &Config) -> Doc {
+        vec![
+            Doc::Space,
+            self.else_if_keyword.format(config),
+            Doc::Space,
+            self.condition.format(config),
+            Doc::Line,
+            self.then_keyword.format(config),
+            Doc::Space,
+            self.expression.format(config),
+        ]
+        .into()
     }
 }
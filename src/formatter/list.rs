@@ -1,104 +1,92 @@
 //! All `impl` blocks for [`List`].
 
-use luau_parser::types::{List, ListItem};
+use luau_parser::{
+    prelude::Token,
+    types::{List, ListItem},
+};
 
 use crate::{
     config::Config,
-    traits::{Format, FormatWithArgs, Indentation},
+    doc::Doc,
+    traits::{Format, FormatWithArgs},
 };
 
-use super::trivia::TriviaFormattingType;
+use super::trivia::{TriviaFormattingType, has_line_breaking_comments};
 
-impl<T: Format> FormatWithArgs<&str> for List<T> {
-    fn format_with(&self, indentation: Indentation, config: &Config, separator: &str) -> String {
-        let mut string = String::new();
+/// What separates the items of a [`List`], after the comma between them.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub(crate) enum ListSeparator {
+    /// A space. Items are only put on the next line if the comments before
+    /// them need it.
+    Space,
 
-        for item in self.iter() {
-            string.push_str(&item.format_with(indentation, config, separator));
-        }
-
-        string
-    }
+    /// A space, or a line break if the group around the list breaks.
+    Line,
 }
-impl<A: Clone, T: FormatWithArgs<A>> FormatWithArgs<(&str, A)> for List<T> {
-    fn format_with(
-        &self,
-        indentation: Indentation,
-        config: &Config,
-        (separator, args): (&str, A),
-    ) -> String {
-        let mut string = String::new();
 
-        for item in self.iter() {
-            string.push_str(&item.format_with(indentation, config, (separator, args.clone())));
-        }
+/// Formats the passed separator, as `separator` instead of its own text, with the
+/// comments around it.
+pub(crate) fn format_separator(
+    original_separator: &Token,
+    separator: &str,
+    config: &Config,
+) -> Doc {
+    let mut leading_comments = original_separator
+        .leading_trivia
+        .format_with(config, TriviaFormattingType::LeadingComments);
+    leading_comments.trim_trailing_space();
 
-        string
-    }
+    vec![
+        leading_comments,
+        Doc::text(separator),
+        original_separator
+            .trailing_trivia
+            .format_with(config, TriviaFormattingType::TrailingComments),
+    ]
+    .into()
 }
 
-impl<T: Format> FormatWithArgs<&str> for ListItem<T> {
-    fn format_with(&self, indentation: Indentation, config: &Config, separator: &str) -> String {
-        match self {
-            Self::Trailing {
-                item,
-                separator: original_separator,
-            } => {
-                item.format(indentation, config)
-                    + &original_separator.leading_trivia.format_with(
-                        indentation,
-                        config,
-                        TriviaFormattingType::CommentsOnly,
-                    )
-                    + separator
-            }
-            Self::NonTrailing(item) => item.format(indentation, config),
-        }
-    }
-}
-impl<A, T: FormatWithArgs<A>> FormatWithArgs<(&str, A)> for ListItem<T> {
-    fn format_with(
-        &self,
-        indentation: Indentation,
-        config: &Config,
-        (separator, args): (&str, A),
-    ) -> String {
-        match self {
-            Self::Trailing {
-                item,
-                separator: original_separator,
-            } => {
-                let final_spaces = &original_separator.trailing_trivia.format_with(
-                    indentation,
-                    config,
-                    TriviaFormattingType::SpacesOnly,
-                );
+impl<T: Format> FormatWithArgs<ListSeparator> for List<T> {
+    fn format_with(&self, config: &Config, list_separator: ListSeparator) -> Doc {
+        let mut docs = Vec::new();
+        let mut needs_line_break = false;
 
-                // We check for newlines instead of the config's newline_style since the user
-                // may not be using that style by default. \n is guaranteed to exist in any
-                // new line.
-                let string = item.format_with(indentation, config, args)
-                    + &original_separator.leading_trivia.format_with(
-                        indentation,
-                        config,
-                        TriviaFormattingType::CommentsOnly,
-                    );
+        for item in self.iter() {
+            let item_doc = match item {
+                ListItem::Trailing { item, separator } => {
+                    let mut item = item.format(config);
+                    item.trim_trailing_space();
+
+                    vec![item, format_separator(separator, ",", config)].into()
+                }
+                ListItem::NonTrailing(item) => item.format(config),
+            };
 
-                if final_spaces.matches('\n').nth(1).is_some() {
-                    // At least 2 spaces exist, so we limit to 2
-                    // remove trailing spaces from the first one, then add the
-                    // second one without including the separator itself
-                    // (which is just the first character).
-                    string
-                        + separator.trim_end()
-                        + config.newline_style.as_str()
-                        + config.newline_style.as_str()
-                        + &config.indent_style.to_string(indentation + 1, config)
-                } else {
-                    string + separator
+            match list_separator {
+                ListSeparator::Line if !docs.is_empty() => {
+                    docs.push(Doc::Line);
+                    docs.push(item_doc);
                 }
+                // The comments before the item can't be on the line of the
+                // previous one.
+                ListSeparator::Space if needs_line_break => {
+                    docs.push(Doc::indent(vec![Doc::HardLine, item_doc].into()));
+                }
+                ListSeparator::Space if !docs.is_empty() => {
+                    docs.push(Doc::Space);
+                    docs.push(item_doc);
+                }
+                _ => docs.push(item_doc),
             }
-            Self::NonTrailing(item) => item.format_with(indentation, config, args),
+
+            needs_line_break = match item {
+                ListItem::Trailing { separator, .. } => {
+                    has_line_breaking_comments(&separator.trailing_trivia)
+                }
+                ListItem::NonTrailing(_) => false,
+            };
         }
+
+        docs.into()
     }
 }
@@ -1,42 +1,20 @@
 //! Helper macros.
 
-/// Formats the parameters and returns. Expands the parameters if they're too long.
+/// Formats the parameters and returns. The parameters are put on their own lines
+/// if they don't fit on the line.
 macro_rules! handle_parameters_and_returns {
     (
         ( $parameters:expr, $(+ $space:literal +)? $symbol:expr, $return_type:expr ),
-        $string: ident,
-        $indentation: ident,
+        $docs: ident,
         $config: ident
     ) => {
-        let start = $string.rfind('\n').unwrap_or_default();
-        {
-            let parameters = $parameters.format_with($indentation + 1, $config, ", ");
-
-            if $string.len() + parameters.len() - start > $config.column_width {
-                $string.push_str(&$parameters.expand_with(
-                    $indentation + 1,
-                    $config,
-                    &(",".to_string()
-                        + $config.newline_style.as_str()
-                        + &$config.indent_style.to_string($indentation + 1, $config))
-                ));
-            } else {
-                $string.push_str(&parameters);
-            }
-        }
+        $docs.push($parameters.format_with($config, $crate::formatter::list::ListSeparator::Line));
 
         if let Some(symbol) = &$symbol {
-            $( $string.push($space); )?
-            $string.push_str(&symbol.format($indentation, $config));
-            $string.push(' ');
-
-            let returns = $return_type.format($indentation, $config);
-
-            if $string.len() + returns.len() - start > $config.column_width {
-                $string.push_str(&$return_type.expand($indentation, $config));
-            } else {
-                $string.push_str(&returns);
-            }
+            $( $docs.push($crate::doc::Doc::text($space)); )?
+            $docs.push(symbol.format($config));
+            $docs.push($crate::doc::Doc::Space);
+            $docs.push($return_type.format($config));
         }
     };
 }
@@ -44,17 +22,15 @@ macro_rules! handle_parameters_and_returns {
 /// A helper function for [`format_function!`] macro. It's mainly to handle type
 /// functions since they may have a preceeding `export` keyword.
 macro_rules! format_function_start_inner {
-    ($self:ident . $export:ident, $indentation: ident, $config: ident) => {
+    ($self:ident . $export:ident, $config: ident) => {
         if $self.$export.is_some() {
-            let mut string = $self.$export.format($indentation, $config);
-            string.push(' ');
-            string
+            vec![$self.$export.format($config), $crate::doc::Doc::Space]
         } else {
-            String::new()
+            Vec::new()
         }
     };
-    ($self: ident, $indentation: ident, $config: ident) => {
-        $self.attributes.format($indentation, $config)
+    ($self: ident, $config: ident) => {
+        vec![$self.attributes.format($config)]
     };
 }
 
@@ -62,32 +38,38 @@ macro_rules! format_function_start_inner {
 macro_rules! format_function {
     (
         $self: ident,
-        $indentation: ident,
         $config: ident,
         $(let export = $export:ident;)?
         $(let keyword = $keyword:ident;)?
         $(let name = $function_name:ident;)?
     ) => {{
-        let mut string = format_function_start_inner!($self $(. $export)?, $indentation, $config);
+        let mut docs = format_function_start_inner!($self $(. $export)?, $config);
         $(
-            string.push_str(&$self.$keyword.format($indentation, $config));
-            string.push(' ');
+            docs.push($self.$keyword.format($config));
+            docs.push($crate::doc::Doc::Space);
         )?
-        string.push_str(&$self.function_keyword.format($indentation, $config));
+        docs.push($self.function_keyword.format($config));
         $(
-            string.push(' ');
-            string.push_str(&$self.$function_name.format($indentation, $config));
+            docs.push($crate::doc::Doc::Space);
+            docs.push($self.$function_name.format($config));
         )?
-        string.push_str(&$self.generics.format_with($indentation, $config, ", "));
+        docs.push(
+            $self
+                .generics
+                .format_with($config, $crate::formatter::list::ListSeparator::Line),
+        );
         handle_parameters_and_returns!(
             ($self.parameters, $self.colon, $self.return_type),
-            string,
-            $indentation,
+            docs,
             $config
         );
-        string.push_str(&$self.body.format($indentation + 1, $config));
-        string.push_str(&$self.end_keyword.format($indentation, $config));
+        docs.push($crate::formatter::block::format_body(&$self.body, $config));
+        docs.push($crate::formatter::block::format_closing_keyword(
+            &$self.body,
+            &$self.end_keyword,
+            $config,
+        ));
 
-        string
+        $crate::doc::Doc::from(docs)
     }};
 }
@@ -11,148 +11,336 @@ mod name;
 mod trivia;
 mod value;
 
+pub(crate) use block::{BlockType, format_block, get_block_type, get_statements};
+
 use luau_parser::prelude::{Literal, LuauString, Pointer, Token, TokenType};
 use trivia::TriviaFormattingType;
 
 use crate::{
-    config::{Config, NamingConvention},
-    traits::{Expand, Format, FormatWithArgs, Indentation},
+    config::{Config, StringWrap},
+    doc::{Doc, print, width},
+    interpolation::{Segment, expression_of, parse_expression, split_interpolated_string},
+    traits::{Format, FormatWithArgs},
 };
 
-impl Format for LuauString {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        let string = config.quote_style.apply(self);
+/// Removes the `\z` escapes, and the whitespace they skip, from the passed
+/// string, as they'll be added again where needed when splitting it.
+fn remove_z_escapes(string: &str) -> String {
+    let mut result = String::with_capacity(string.len());
+    let mut chars = string.chars().peekable();
 
-        if string.len() > config.string_width {
-            let separator = r"\z".to_string()
-                + &config.newline_style.to_string()
-                + &config.indent_style.to_string(indentation + 1, config);
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            result.push(char);
+            continue;
+        }
 
-            let words = string.split_whitespace().collect::<Vec<&str>>();
-            let mut current_line = String::new();
-            let mut result = String::new();
+        match chars.next() {
+            Some('z') => {
+                while chars.next_if(char::is_ascii_whitespace).is_some() {}
+            }
+            Some(escaped) => {
+                result.push(char);
+                result.push(escaped);
+            }
+            None => result.push(char),
+        }
+    }
 
-            for word in words {
-                if current_line.len() + word.len() + 1 > config.string_width {
-                    if !current_line.is_empty() {
-                        result.push_str(&current_line);
-                        result.push_str(&separator);
-                    }
+    result
+}
 
-                    current_line = word.to_string();
-                } else {
-                    current_line.push_str(word);
-                }
+/// Splits the content of a quoted string, without its quotes, into lines that
+/// fit in [`Config::string_width`] with a quote, or a `\z`, on each side. Lines
+/// only end after whitespace that isn't escaped, and keep it, so escape
+/// sequences aren't split and the lines make up the whole content again.
+fn split_string<'a>(content: &'a str, config: &Config) -> Vec<&'a str> {
+    let mut words = Vec::new();
+    let mut word_start = 0;
+    let mut is_escaped = false;
+    let mut is_after_whitespace = false;
 
-                current_line.push(' ');
+    for (i, char) in content.char_indices() {
+        if is_after_whitespace && !char.is_whitespace() && i > word_start {
+            words.push(&content[word_start..i]);
+            word_start = i;
+        }
+
+        is_after_whitespace = !is_escaped && char.is_whitespace();
+        is_escaped = !is_escaped && char == '\\';
+    }
+    words.push(&content[word_start..]);
+
+    let mut lines = Vec::new();
+    let mut line_start = 0;
+    let mut line_width = 2;
+
+    for word in words {
+        let word_start = word.as_ptr() as usize - content.as_ptr() as usize;
+        let word_width = width(word, config);
+
+        if line_start < word_start && line_width + word_width > config.string_width {
+            lines.push(&content[line_start..word_start]);
+            line_start = word_start;
+            line_width = 2;
+        }
+
+        line_width += word_width;
+    }
+    lines.push(&content[line_start..]);
+
+    lines
+}
+
+/// Splits the passed quoted string, with its quotes, into the contents of the
+/// lines it's wrapped on, if it's longer than [`Config::string_width`]. Strings
+/// with escaped line breaks are kept as they are.
+fn wrap_string(string: &str, config: &Config) -> Option<Vec<String>> {
+    if config.string_wrap() == StringWrap::Never || width(string, config) <= config.string_width {
+        return None;
+    }
+
+    let string = remove_z_escapes(string);
+    let content = &string[1..string.len() - 1];
+    if content.contains(['\n', '\r']) {
+        return None;
+    }
+
+    Some(
+        split_string(content, config)
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Formats the expressions of the passed interpolated string, each on a single
+/// line, as the string can't be split. The ones that can't be formatted, or
+/// would be split anyway, are kept as they are.
+fn format_interpolated_string(string: &str, config: &Config) -> String {
+    let expression_config = Config {
+        column_width: usize::MAX / 2,
+        string_wrap: StringWrap::Never,
+        ..config.clone()
+    };
+    let mut formatted_string = String::with_capacity(string.len());
+
+    for segment in split_interpolated_string(string) {
+        let code = match segment {
+            Segment::Text(text) => {
+                formatted_string.push_str(text);
+                continue;
             }
+            Segment::Expression(code) => code,
+        };
+
+        let expression = parse_expression(code)
+            .and_then(|cst| {
+                let expression = expression_of(&cst)?.format(&expression_config);
+                Some(print(&expression, 0, &expression_config))
+            })
+            .map(|expression| expression.trim().to_string())
+            .filter(|expression| !expression.contains(['\n', '\r']));
 
-            if !current_line.is_empty() {
-                result.push_str(&current_line);
+        match expression {
+            Some(expression)
+                if config.interpolation_padding || expression.starts_with('{') =>
+            {
+                formatted_string.push_str(&format!("{{ {expression} }}"))
             }
+            Some(expression) => formatted_string.push_str(&format!("{{{expression}}}")),
+            None => formatted_string.push_str(&format!("{{{code}}}")),
+        }
+    }
 
-            result.pop(); // remove the final space
-            result
-        } else {
-            config.quote_style.apply(self)
+    formatted_string
+}
+
+impl Format for LuauString {
+    fn format(&self, config: &Config) -> Doc {
+        if let Self::Backticks(string) = self {
+            return Doc::Text(format_interpolated_string(string, config));
         }
+
+        let string = config.quote_style.apply(self);
+        let is_quoted = matches!(self, Self::SingleQuotes(_) | Self::DoubleQuotes(_));
+
+        // `\z` would be part of the string's content in multi-line strings, and
+        // would split the expressions of interpolated strings.
+        let lines = match config.string_wrap() {
+            StringWrap::Escape if is_quoted => wrap_string(&string, config),
+            StringWrap::Escape | StringWrap::Concatenation | StringWrap::Never => None,
+        };
+        let Some(lines) = lines else {
+            return Doc::Text(string);
+        };
+
+        let quote = &string[..1];
+        let last_index = lines.len() - 1;
+        let mut docs = vec![Doc::text(quote)];
+        for (i, line) in lines.into_iter().enumerate() {
+            docs.push(Doc::Text(line));
+
+            if i < last_index {
+                docs.push(Doc::text(r"\z"));
+                docs.push(Doc::TokenLine);
+            }
+        }
+        docs.push(Doc::text(quote));
+
+        Doc::indent(docs.into())
     }
 }
 
-/// Whether the formatted token is one of the passed ones.
-#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub enum TokenFormatType {
-    /// A type name.
-    Type,
+/// Gets the strings the passed string token is split into, to join them with
+/// `..`, if [`Config::string_wrap`] is [`StringWrap::Concatenation`] and it's
+/// too long. Each of them gets the quotes [`Config::quote_style`] picks for it.
+fn concatenated_strings(token: &Token, config: &Config) -> Option<Vec<String>> {
+    let TokenType::Literal(Literal::String(
+        luau_string @ (LuauString::SingleQuotes(_) | LuauString::DoubleQuotes(_)),
+    )) = &token.token_type
+    else {
+        return None;
+    };
+    if config.string_wrap() != StringWrap::Concatenation {
+        return None;
+    }
+
+    let string = config.quote_style.apply(luau_string);
+    let quote = &string[..1];
+    let lines = wrap_string(&string, config)?;
+
+    Some(
+        lines
+            .into_iter()
+            .map(|line| {
+                let line = format!("{quote}{line}{quote}");
+                let luau_string = if quote == "'" {
+                    LuauString::SingleQuotes(line.into())
+                } else {
+                    LuauString::DoubleQuotes(line.into())
+                };
+
+                config.quote_style.apply(&luau_string)
+            })
+            .collect(),
+    )
+}
+
+/// Whether or not the passed string token is split into several strings joined
+/// with `..`, see [`format_string_expression`].
+pub(crate) fn is_concatenated(token: &Token, config: &Config) -> bool {
+    concatenated_strings(token, config).is_some_and(|strings| strings.len() > 1)
+}
+
+/// Formats the passed string token, used as an expression, split into several
+/// strings joined with `..` if [`Config::string_wrap`] is
+/// [`StringWrap::Concatenation`] and it's too long. They're laid out like any
+/// other strings joined with `..` on several lines, and put between
+/// parentheses, like any other parenthesized expression, if `is_operand` is
+/// set, as the string is then an operand of an operator, which could bind
+/// tighter than `..`.
+pub(crate) fn format_string_expression(token: &Token, is_operand: bool, config: &Config) -> Doc {
+    let Some(strings) = concatenated_strings(token, config) else {
+        return token.format(config);
+    };
+    let is_concatenated = strings.len() > 1;
 
-    /// A method name.
-    Method,
+    let mut strings = strings.into_iter().map(Doc::Text);
+    let first = strings.next().unwrap_or_default();
+    let mut docs = Vec::new();
+    for string in strings {
+        docs.push(Doc::HardLine);
+        docs.push(Doc::text(".."));
+        docs.push(Doc::Space);
+        docs.push(string);
+    }
 
-    /// A variable name.
-    Name,
+    let mut string = Doc::group_with(vec![first, Doc::indent_if_break(docs.into())].into(), true);
+    if is_operand && is_concatenated {
+        string = Doc::group(
+            vec![
+                Doc::text("("),
+                Doc::indent_if_break(vec![Doc::SoftLine, string].into()),
+                Doc::SoftLine,
+                Doc::text(")"),
+            ]
+            .into(),
+        );
+    }
 
-    /// Not even a name.
-    #[default]
-    None,
+    vec![
+        token
+            .leading_trivia
+            .format_with(config, TriviaFormattingType::LeadingComments),
+        string,
+        token
+            .trailing_trivia
+            .format_with(config, TriviaFormattingType::TrailingComments),
+    ]
+    .into()
 }
 
-impl FormatWithArgs<TokenFormatType> for Token {
-    fn format_with(
-        &self,
-        indentation: Indentation,
-        config: &Config,
-        token_format_type: TokenFormatType,
-    ) -> String {
+/// Formats the passed token without the comments of its leading trivia, which
+/// are formatted separately, like the comments at the end of a body before the
+/// keyword closing it.
+pub(crate) fn format_without_leading_comments(token: &Token, config: &Config) -> Doc {
+    vec![
+        Doc::text(token.token_type.try_as_string().unwrap_or_default()),
+        token
+            .trailing_trivia
+            .format_with(config, TriviaFormattingType::TrailingComments),
+    ]
+    .into()
+}
+
+impl Format for Token {
+    fn format(&self, config: &Config) -> Doc {
         let token_type = match &self.token_type {
-            TokenType::Literal(Literal::String(luau_string)) => {
-                luau_string.format(indentation, config)
-            }
-            TokenType::Identifier(identifier) => match token_format_type {
-                TokenFormatType::Type => config.type_casing.apply(identifier),
-                TokenFormatType::Method => config.method_casing.apply(identifier),
-                TokenFormatType::Name => config.variable_casing.apply(identifier),
-                TokenFormatType::None => NamingConvention::None.apply(identifier),
-            },
-            TokenType::EndOfFile => "".to_string(),
+            TokenType::Literal(Literal::String(luau_string)) => luau_string.format(config),
+            TokenType::EndOfFile => Doc::Nil,
 
             // `unwrap` itself is safe and should never error as this will only be
             // be called by the library, which checks for the CST's correctness
             // before starting any of the formatting.
-            token_type => token_type.try_as_string().unwrap_or_default(),
+            token_type => Doc::text(token_type.try_as_string().unwrap_or_default()),
         };
 
-        self.leading_trivia
-            .format_with(indentation, config, TriviaFormattingType::CommentsOnly)
-            + &token_type
-    }
-}
-
-impl Format for Token {
-    #[inline]
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        self.format_with(indentation, config, TokenFormatType::None)
+        vec![
+            self.leading_trivia
+                .format_with(config, TriviaFormattingType::LeadingComments),
+            token_type,
+            self.trailing_trivia
+                .format_with(config, TriviaFormattingType::TrailingComments),
+        ]
+        .into()
     }
 }
 
 impl<T: Format> Format for Option<T> {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         match self {
-            Some(item) => item.format(indentation, config),
-            None => String::new(),
+            Some(item) => item.format(config),
+            None => Doc::Nil,
         }
     }
 }
 impl<A, T: FormatWithArgs<A>> FormatWithArgs<A> for Option<T> {
-    fn format_with(&self, indentation: Indentation, config: &Config, args: A) -> String {
+    fn format_with(&self, config: &Config, args: A) -> Doc {
         match self {
-            Some(item) => item.format_with(indentation, config, args),
-            None => String::new(),
-        }
-    }
-}
-
-impl<T: Expand> Expand for Option<T> {
-    fn expand(&self, indentation: Indentation, config: &Config) -> String {
-        match self {
-            Some(item) => item.expand(indentation, config),
-            None => String::new(),
+            Some(item) => item.format_with(config, args),
+            None => Doc::Nil,
         }
     }
 }
 
 impl<T: Format> Format for Pointer<T> {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
-        (**self).format(indentation, config)
+    fn format(&self, config: &Config) -> Doc {
+        (**self).format(config)
     }
 }
 impl<A, T: FormatWithArgs<A>> FormatWithArgs<A> for Pointer<T> {
-    fn format_with(&self, indentation: Indentation, config: &Config, args: A) -> String {
-        (**self).format_with(indentation, config, args)
-    }
-}
-
-impl<T: Expand> Expand for Pointer<T> {
-    fn expand(&self, indentation: Indentation, config: &Config) -> String {
-        (**self).expand(indentation, config)
+    fn format_with(&self, config: &Config, args: A) -> Doc {
+        (**self).format_with(config, args)
     }
 }
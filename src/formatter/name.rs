@@ -4,25 +4,22 @@ use luau_parser::types::Name;
 
 use crate::{
     config::Config,
-    traits::{Format, FormatWithArgs, Indentation},
+    doc::Doc,
+    traits::Format,
 };
 
-use super::TokenFormatType;
-
 impl Format for Name {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         if let Some(r#type) = self.r#type.as_ref() {
-            let mut string = self
-                .name
-                .format_with(indentation, config, TokenFormatType::Name);
-            string.push_str(&self.colon.format(indentation, config));
-            string.push(' ');
-            string.push_str(&r#type.format(indentation, config));
-
-            string
+            vec![
+                self.name.format(config),
+                self.colon.format(config),
+                Doc::Space,
+                r#type.format(config),
+            ]
+            .into()
         } else {
-            self.name
-                .format_with(indentation, config, TokenFormatType::Name)
+            self.name.format(config)
         }
     }
 }
@@ -7,18 +7,18 @@ use luau_parser::{
 
 use crate::{
     config::Config,
-    traits::{Format, FormatWithArgs, Indentation},
+    doc::{Doc, width},
+    traits::{Format, FormatWithArgs},
 };
 
 /// Whether or not the current line starts with ` ``` `. This also checks for a
-/// maximum of 3 preceeding spaces (while accounting for indentation) since that
-/// still counts as a code block in markdown.
-fn is_triple_backticks(line: &str, indentation: Indentation, config: &Config) -> bool {
+/// maximum of 3 preceeding spaces since that still counts as a code block in
+/// markdown.
+fn is_triple_backticks(line: &str, config: &Config) -> bool {
     let mut chars = line.chars();
     let mut space_count = 0;
     let mut tabs_count = 0;
-    let indent_size = config.tab_size as Indentation;
-    let max_indentation = config.tab_size as Indentation * indentation + 3;
+    let indent_size = config.tab_size;
 
     loop {
         match chars.next() {
@@ -27,7 +27,7 @@ fn is_triple_backticks(line: &str, indentation: Indentation, config: &Config) ->
             _ => break,
         }
 
-        if space_count + tabs_count * indent_size > max_indentation {
+        if space_count + tabs_count * indent_size > 3 {
             return false;
         }
     }
@@ -35,150 +35,256 @@ fn is_triple_backticks(line: &str, indentation: Indentation, config: &Config) ->
     chars.as_str().starts_with("```")
 }
 
-/// Used for comment formatting. Takes the passed text and expands it to multiple
-/// lines, with every line starting with the passed prefix.
-fn wrap_text_with_prefix(
-    text: &str,
-    prefix: &str,
-    indentation: Indentation,
-    config: &Config,
-) -> String {
-    let mut string = String::new();
-    let available_width = config.comments_width.saturating_sub(prefix.len());
-    let mut found_empty = false;
+/// Used for comment formatting. Splits the passed text into lines that fit in
+/// [`Config::comments_width`] once the passed prefix is put before them.
+fn wrap_text_with_prefix(text: &str, prefix: &str, config: &Config) -> Vec<String> {
+    let available_width = config.comments_width.saturating_sub(width(prefix, config));
+    let mut lines: Vec<String> = Vec::new();
     let mut is_in_code_block = false;
 
-    for line in text.split('\n') {
+    for line in text.trim().split('\n') {
         let line = line.trim();
-        if is_triple_backticks(line, indentation, config) {
+        if is_triple_backticks(line, config) {
             is_in_code_block = !is_in_code_block
         }
 
         if line.is_empty() {
-            if is_in_code_block {
-                string.push_str(prefix);
-            } else if !found_empty {
-                string.push_str(prefix);
-
-                found_empty = true;
-            } else {
-                found_empty = false;
+            // Consecutive empty lines are merged into one, unless they're in a
+            // code block.
+            if is_in_code_block || lines.last().is_some_and(|line| !line.is_empty()) {
+                lines.push(String::new());
             }
 
             continue;
         }
 
-        let words = line.split_whitespace();
         let mut current_line = String::new();
 
-        for word in words {
-            if current_line.len() + word.len() + 1 > available_width {
-                string.push_str(current_line.trim_end());
-                string.push_str(config.newline_style.as_str());
-                current_line = prefix.to_string();
+        for word in line.split_whitespace() {
+            if !current_line.is_empty()
+                && width(&current_line, config) + width(word, config) + 1 > available_width
+            {
+                lines.push(current_line);
+                current_line = String::new();
             }
 
+            if !current_line.is_empty() {
+                current_line.push(' ');
+            }
             current_line.push_str(word);
-            current_line.push(' ');
         }
 
-        string.push_str(current_line.trim_end());
+        lines.push(current_line);
     }
 
-    string
+    lines
+}
+
+/// Joins the passed lines of a comment with line breaks, putting the passed
+/// prefix before every line except the first one.
+fn join_lines(lines: Vec<String>, prefix: &str) -> Doc {
+    let mut docs = Vec::new();
+
+    for (i, line) in lines.into_iter().enumerate() {
+        if i > 0 {
+            docs.push(Doc::HardLine);
+            docs.push(Doc::text(prefix));
+        }
+
+        docs.push(Doc::Text(line));
+    }
+
+    docs.into()
 }
 
 impl Format for Comment {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         let string = self.print();
 
-        if string.len() > config.comments_width {
-            match self {
-                Comment::SingleLine(comment) => {
-                    "-- ".to_string()
-                        + &wrap_text_with_prefix(
-                            &comment.trim_start()[2..],
-                            &(config.indent_style.to_string(indentation, config) + "-- "),
-                            indentation,
-                            config,
-                        )
-                }
-                Comment::MultiLine(comment) => {
-                    let (comment_start, comment_end) = {
-                        let stripped_comment = &comment[3..comment.len() - 1];
-                        let comment_start = stripped_comment.find('[').unwrap();
-                        let comment_end = stripped_comment.rfind(']').unwrap();
-
-                        (comment_start + 4, comment_end + 3)
-                    };
-                    let (start, end) = (&comment[..comment_start], &comment[comment_end..]);
-
-                    let prefix = config.indent_style.to_string(indentation + 1, config);
-
-                    let mut string = start.to_string();
-                    string.push_str(config.newline_style.as_str());
-                    string.push_str(&prefix);
-                    string.push_str(&wrap_text_with_prefix(
-                        &comment[comment_start..comment_end],
-                        &prefix,
-                        indentation,
-                        config,
-                    ));
-                    string.push_str(config.newline_style.as_str());
-                    string.push_str(end);
-
-                    string
-                }
+        if width(&string, config) <= config.comments_width {
+            return Doc::Text(string);
+        }
+
+        match self {
+            Comment::SingleLine(comment) => vec![
+                Doc::text("-- "),
+                join_lines(
+                    wrap_text_with_prefix(&comment.trim_start()[2..], "-- ", config),
+                    "-- ",
+                ),
+            ]
+            .into(),
+            Comment::MultiLine(comment) => {
+                let (comment_start, comment_end) = {
+                    let stripped_comment = &comment[3..comment.len() - 1];
+                    let comment_start = stripped_comment.find('[').unwrap();
+                    let comment_end = stripped_comment.rfind(']').unwrap();
+
+                    (comment_start + 4, comment_end + 3)
+                };
+                let (start, end) = (&comment[..comment_start], &comment[comment_end..]);
+
+                vec![
+                    Doc::text(start),
+                    Doc::indent(
+                        vec![
+                            Doc::HardLine,
+                            join_lines(
+                                wrap_text_with_prefix(
+                                    &comment[comment_start..comment_end],
+                                    &config.indent_style.to_string(1, config),
+                                    config,
+                                ),
+                                "",
+                            ),
+                        ]
+                        .into(),
+                    ),
+                    Doc::HardLine,
+                    Doc::text(end),
+                ]
+                .into()
             }
+        }
+    }
+}
+
+/// Splits the passed trivia at its first line break. The first part is on the
+/// same line as the token before the trivia, the second one is on the lines
+/// before the token after it.
+pub(crate) fn split_at_line_break(trivia: &[Trivia]) -> (&[Trivia], &[Trivia]) {
+    let index = trivia
+        .iter()
+        .position(|trivia| matches!(trivia, Trivia::Spaces(spaces) if spaces.contains('\n')))
+        .unwrap_or(trivia.len());
+
+    trivia.split_at(index)
+}
+
+/// Whether or not the comments of the passed trivia force a line break, which is
+/// the case for single-line comments and for comments on their own lines.
+pub(crate) fn has_line_breaking_comments(trivia: &[Trivia]) -> bool {
+    let (same_line, own_lines) = split_at_line_break(trivia);
+
+    same_line
+        .iter()
+        .any(|trivia| matches!(trivia, Trivia::Comment(Comment::SingleLine(_))))
+        || own_lines
+            .iter()
+            .any(|trivia| matches!(trivia, Trivia::Comment(_)))
+}
+
+/// Counts the line breaks of the passed trivia before its first comment on its
+/// own line, as the ones after it are formatted with it.
+pub(crate) fn count_line_breaks(trivia: &[Trivia]) -> usize {
+    let (same_line, own_lines) = split_at_line_break(trivia);
+    let spaces = |trivia: &Trivia| match trivia {
+        Trivia::Spaces(spaces) => Some(spaces.matches('\n').count()),
+        Trivia::Comment(_) => None,
+    };
+
+    same_line.iter().filter_map(spaces).sum::<usize>()
+        + own_lines.iter().map_while(spaces).sum::<usize>()
+}
+
+/// Formats the comments of the passed trivia as comments on their own lines, the
+/// first one starting a line if it isn't already. Each of them is followed by
+/// the line breaks after it, up to 2 unless
+/// [`Config::keep_statements_spacing`] is set. Multi-line comments with no line
+/// break after them are followed by a space instead, to stay on the line of the
+/// token after them.
+pub(crate) fn format_own_line_comments(trivia: &[Trivia], config: &Config) -> Vec<Doc> {
+    let mut docs = Vec::new();
+
+    for (i, item) in trivia.iter().enumerate() {
+        let Trivia::Comment(comment) = item else {
+            continue;
+        };
+
+        if docs.is_empty() {
+            docs.push(Doc::BreakParent);
+            docs.push(Doc::LineStart);
+        }
+        docs.push(comment.format(config));
+
+        let new_lines = match trivia.get(i + 1) {
+            Some(Trivia::Spaces(spaces)) => spaces.matches('\n').count(),
+            _ => 0,
+        };
+        let new_lines = if matches!(comment, Comment::SingleLine(_)) {
+            new_lines.max(1)
+        } else {
+            new_lines
+        };
+
+        if new_lines == 0 {
+            docs.push(Doc::Space);
+        } else if config.keep_statements_spacing {
+            docs.extend((0..new_lines).map(|_| Doc::HardLine));
         } else {
-            string
+            docs.extend((0..new_lines.min(2)).map(|_| Doc::HardLine));
         }
     }
+
+    docs
+}
+
+/// Formats the comments of the passed trivia as comments on the same line as the
+/// token before them. They're kept as-is, as wrapping them would move them to
+/// the next lines. `is_followed_by_token` is whether the token after them is on
+/// that line too, in which case they stay before it, with a space between them.
+/// Otherwise, they end the line, so they're put at the end of the line the
+/// token before them ends up on, which also keeps single-line comments from
+/// commenting out code.
+fn format_same_line_comments(trivia: &[Trivia], is_followed_by_token: bool) -> Doc {
+    let mut docs = Vec::new();
+    let mut has_single_line_comment = false;
+
+    for item in trivia {
+        let Trivia::Comment(comment) = item else {
+            continue;
+        };
+
+        docs.push(Doc::Space);
+        docs.push(Doc::Comment(comment.print().trim_end().to_string()));
+        has_single_line_comment |= matches!(comment, Comment::SingleLine(_));
+    }
+
+    if docs.is_empty() {
+        Doc::Nil
+    } else if is_followed_by_token && !has_single_line_comment {
+        docs.push(Doc::Space);
+        docs.into()
+    } else {
+        Doc::line_suffix(docs.into())
+    }
 }
 
-/// Formatting types for [`[Trivia]`](Trivia).
+/// Formatting types for [`[Trivia]`](Trivia). The trailing trivia of a token is
+/// also the leading trivia of the next one, so comments on the same line as a
+/// token are formatted with it, and the ones on the next lines are formatted
+/// with the next token.
 pub enum TriviaFormattingType {
-    /// Include only spaces.
-    SpacesOnly,
+    /// Include only the comments on their own lines, see
+    /// [`format_own_line_comments`]. These are put before the token whose leading
+    /// trivia is formatted.
+    LeadingComments,
 
-    /// Include only comments, this'll include the space after every comment to avoid
-    /// syntactical errors.
-    CommentsOnly,
+    /// Include only the comments on the same line as the token whose trailing
+    /// trivia is formatted, see [`format_same_line_comments`].
+    TrailingComments,
 }
 
 impl FormatWithArgs<TriviaFormattingType> for [Trivia] {
-    fn format_with(
-        &self,
-        indentation: Indentation,
-        config: &Config,
-        trivia_formatting_type: TriviaFormattingType,
-    ) -> String {
+    fn format_with(&self, config: &Config, trivia_formatting_type: TriviaFormattingType) -> Doc {
+        let (same_line, own_lines) = split_at_line_break(self);
+
         match trivia_formatting_type {
-            TriviaFormattingType::SpacesOnly => {
-                self.iter().fold(String::new(), |str, trivia| match trivia {
-                    Trivia::Spaces(smol_str) => str + smol_str,
-                    Trivia::Comment(_) => str,
-                })
+            TriviaFormattingType::LeadingComments => {
+                format_own_line_comments(own_lines, config).into()
             }
-            TriviaFormattingType::CommentsOnly => {
-                let mut found_comment = false;
-
-                self.iter().fold(String::new(), |str, trivia| match trivia {
-                    Trivia::Spaces(smol_str) => {
-                        if found_comment {
-                            found_comment = false;
-
-                            str + smol_str
-                        } else {
-                            str
-                        }
-                    }
-                    Trivia::Comment(comment) => {
-                        found_comment = true;
-
-                        str + &comment.format(indentation, config)
-                    }
-                })
+            TriviaFormattingType::TrailingComments => {
+                format_same_line_comments(same_line, own_lines.is_empty())
             }
         }
     }
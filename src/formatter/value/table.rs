@@ -5,129 +5,269 @@
 //! * [`TableFieldValue`]
 //! * [`Table`]
 
-use luau_parser::types::{Table, TableField, TableFieldValue, TableKey};
+use luau_parser::types::{ListItem, Table, TableField, TableFieldValue, TableKey};
 
 use crate::{
     config::{CompactTable, Config, TrailingCommas},
-    formatter::TokenFormatType,
-    traits::{Format, FormatWithArgs, Indentation},
+    doc::{Doc, trim_hard_lines},
+    formatter::{
+        format_without_leading_comments,
+        list::format_separator,
+        trivia::{
+            TriviaFormattingType, count_line_breaks, format_own_line_comments,
+            has_line_breaking_comments, split_at_line_break,
+        },
+    },
+    traits::{Format, FormatWithArgs},
 };
 
 impl Format for TableKey {
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         match self {
             Self::ERROR => unreachable!(),
-            Self::UndefinedNumber(_) | Self::UndefinedString(_) => String::new(),
-            Self::Simple(token) => token.format_with(indentation, config, TokenFormatType::Name),
-            Self::Expression(bracketed) => bracketed.format(indentation + 1, config),
-            Self::Type(bracketed) => bracketed.format(indentation + 1, config),
+            Self::UndefinedNumber(_) | Self::UndefinedString(_) => Doc::Nil,
+            Self::Simple(token) => token.format(config),
+            Self::Expression(bracketed) => bracketed.format(config),
+            Self::Type(bracketed) => bracketed.format(config),
         }
     }
 }
 
 impl FormatWithArgs<bool> for TableField {
-    fn format_with(&self, indentation: Indentation, config: &Config, is_type: bool) -> String {
+    fn format_with(&self, config: &Config, is_type: bool) -> Doc {
         if self.equal_or_colon.is_none() {
-            self.value.format(indentation, config)
-        } else if is_type {
-            self.key.format(indentation, config)
-                + &self.equal_or_colon.format(indentation, config)
-                + " "
-                + &self.value.format(indentation, config)
-        } else {
-            self.key.format(indentation, config)
-                + " "
-                + &self.equal_or_colon.format(indentation, config)
-                + " "
-                + &self.value.format(indentation, config)
+            return self.value.format(config);
         }
+
+        vec![
+            self.key.format(config),
+            if is_type { Doc::Nil } else { Doc::Space },
+            self.equal_or_colon.format(config),
+            Doc::Space,
+            self.value.format(config),
+        ]
+        .into()
     }
 }
 
 impl Format for TableFieldValue {
     #[inline]
-    fn format(&self, indentation: Indentation, config: &Config) -> String {
+    fn format(&self, config: &Config) -> Doc {
         match self {
             Self::ERROR => unreachable!(),
-            Self::Expression(expression) => expression.format(indentation + 1, config),
-            Self::Type(type_value) => type_value.format(indentation + 1, config),
-            Self::VariadicValues(token) => token.format(indentation, config),
+            Self::Expression(expression) => expression.format(config),
+            Self::Type(type_value) => type_value.format(config),
+            Self::VariadicValues(token) => token.format(config),
         }
     }
 }
 
-/// Gets the separator between 2 table fields, while accounting for configuration
-/// options and indentation.
-fn get_separator(single_line: bool, indentation: Indentation, config: &Config) -> (String, String) {
-    let spaces = if single_line {
-        " ".to_string()
-    } else {
-        config.newline_style.to_string() + &config.indent_style.to_string(indentation + 1, config)
-    };
+/// How a [`Table`] is laid out.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+enum TableLayout {
+    /// On a single line, even if it's too long.
+    SingleLine,
 
-    let separator = ",".to_string() + &spaces;
+    /// On a single line if it fits, with a field per line otherwise.
+    Fit,
 
-    (spaces, separator)
+    /// With a field per line.
+    MultiLine,
 }
 
-impl FormatWithArgs<bool> for Table {
-    fn format_with(&self, indentation: Indentation, config: &Config, is_type: bool) -> String {
-        if self.0.is_empty() {
-            return self.0.opening_bracket.format(indentation, config)
-                + &self.0.closing_bracket.format(indentation, config);
-        }
+/// Whether or not the passed [`Table`] has comments between its fields that need
+/// line breaks, so it can't be on a single line.
+fn has_line_breaking_comments_table(table: &Table) -> bool {
+    has_line_breaking_comments(&table.0.opening_bracket.trailing_trivia)
+        || has_line_breaking_comments(&table.0.closing_bracket.leading_trivia)
+        || table.0.iter().any(|item| match item {
+            ListItem::Trailing { separator, .. } => {
+                has_line_breaking_comments(&separator.leading_trivia)
+                    || has_line_breaking_comments(&separator.trailing_trivia)
+            }
+            ListItem::NonTrailing(_) => false,
+        })
+}
 
-        let mut single_line = config.compact_table.should_be_single_line(self);
-        let (mut spaces, mut separator) = get_separator(single_line, indentation, config);
+/// Whether or not the passed [`Table`] ends with a separator that keeps it
+/// expanded, see [`Config::magic_trailing_comma`].
+fn has_magic_trailing_comma(table: &Table, config: &Config) -> bool {
+    config.magic_trailing_comma
+        && config.trailing_commas != TrailingCommas::Never
+        && matches!(table.0.last(), Some(ListItem::Trailing { .. }))
+}
 
-        let mut string = "{".to_string()
-            + &spaces
-            + &self
-                .0
-                .item
-                .format_with(indentation, config, (&separator, is_type));
+/// Builds the passed [`Table`] from its formatted fields.
+fn build_table(table: &Table, fields: Vec<Doc>, layout: TableLayout, config: &Config) -> Doc {
+    let line = || match layout {
+        TableLayout::SingleLine => Doc::Space,
+        TableLayout::Fit | TableLayout::MultiLine => Doc::Line,
+    };
+    // A single-line table with a trailing comma would be expanded the next time
+    // if it's magic.
+    let trailing_commas = match config.trailing_commas {
+        TrailingCommas::Always if config.magic_trailing_comma => TrailingCommas::OnlyMultiLine,
+        trailing_commas => trailing_commas,
+    };
+    let trailing_comma = match (trailing_commas, layout) {
+        (TrailingCommas::Always, _) | (TrailingCommas::OnlyMultiLine, TableLayout::MultiLine) => {
+            Doc::text(",")
+        }
+        (TrailingCommas::OnlyMultiLine, TableLayout::Fit) => {
+            Doc::if_break(Doc::text(","), Doc::Nil)
+        }
+        (TrailingCommas::Never, _) | (TrailingCommas::OnlyMultiLine, TableLayout::SingleLine) => {
+            Doc::Nil
+        }
+    };
+    let last_index = fields.len() - 1;
 
-        if single_line
-            && config.compact_table != CompactTable::Always
-            && string.len() > config.column_width
-        {
-            single_line = false;
+    let mut docs = Vec::new();
+    for (i, (mut field, item)) in fields.into_iter().zip(table.0.iter()).enumerate() {
+        // No space is kept between a comment ending the field and its separator.
+        if matches!(item, ListItem::Trailing { .. }) {
+            field.trim_trailing_space();
+        }
 
-            let (new_spaces, new_separator) = get_separator(single_line, indentation, config);
-            string = string.replace(&separator, &new_separator);
-            string.replace_range(1..spaces.len() + 1, &new_spaces);
+        docs.push(line());
+        docs.push(field);
 
-            (spaces, separator) = (new_spaces, new_separator);
-        }
+        match item {
+            ListItem::Trailing { separator, .. } if i == last_index => {
+                docs.push(vec![
+                    separator
+                        .leading_trivia
+                        .format_with(config, TriviaFormattingType::LeadingComments),
+                    trailing_comma.clone(),
+                    separator
+                        .trailing_trivia
+                        .format_with(config, TriviaFormattingType::TrailingComments),
+                ]
+                .into());
+            }
+            ListItem::Trailing { separator, .. } => {
+                docs.push(format_separator(separator, ",", config));
 
-        match config.trailing_commas {
-            TrailingCommas::Never => string = string.trim_end_matches(&separator).to_string(),
-            TrailingCommas::OnlyMultiLine => {
-                let ends_with_separator = string.ends_with(&separator);
-                if !single_line {
-                    if !ends_with_separator {
-                        string.push_str(&separator)
-                    }
-                } else if ends_with_separator {
-                    string.replace_range(
-                        string.len() - separator.len()..string.len() - separator.len() + 1,
-                        "",
-                    );
-                } else {
-                    string.push_str(&spaces);
+                // An empty line between fields is kept.
+                if layout == TableLayout::MultiLine
+                    && count_line_breaks(&separator.trailing_trivia) >= 2
+                {
+                    docs.push(Doc::HardLine);
                 }
             }
-            TrailingCommas::Always if !string.ends_with(&separator) => string.push_str(&separator),
-            TrailingCommas::Always => {}
+            ListItem::NonTrailing(_) if i == last_index => docs.push(trailing_comma.clone()),
+            ListItem::NonTrailing(_) => docs.push(Doc::text(",")),
         }
+    }
+
+    let mut comments = format_own_line_comments(
+        split_at_line_break(&table.0.closing_bracket.leading_trivia).1,
+        config,
+    );
+    trim_hard_lines(&mut comments);
+    if !comments.is_empty() {
+        docs.push(Doc::Line);
+        docs.extend(comments);
+    }
+
+    let docs = vec![
+        table.0.opening_bracket.format(config),
+        Doc::indent_if_break(docs.into()),
+        line(),
+        format_without_leading_comments(&table.0.closing_bracket, config),
+    ];
+
+    match layout {
+        TableLayout::SingleLine => docs.into(),
+        TableLayout::Fit => Doc::group(docs.into()),
+        TableLayout::MultiLine => Doc::group_with(docs.into(), true),
+    }
+}
 
-        if !single_line {
-            // Remove the last indentation.
-            for _ in 0..config.indent_style.to_string(1, config).len() {
-                string.pop();
+impl FormatWithArgs<bool> for Table {
+    fn format_with(&self, config: &Config, is_type: bool) -> Doc {
+        let has_line_breaking_comments = has_line_breaking_comments_table(self);
+
+        if self.0.is_empty() {
+            let mut comments = format_own_line_comments(
+                split_at_line_break(&self.0.closing_bracket.leading_trivia).1,
+                config,
+            );
+            trim_hard_lines(&mut comments);
+            if !comments.is_empty() {
+                comments.insert(0, Doc::SoftLine);
+            } else if !has_line_breaking_comments {
+                return vec![
+                    self.0.opening_bracket.format(config),
+                    format_without_leading_comments(&self.0.closing_bracket, config),
+                ]
+                .into();
             }
+
+            return Doc::group_with(
+                vec![
+                    self.0.opening_bracket.format(config),
+                    Doc::indent_if_break(comments.into()),
+                    Doc::SoftLine,
+                    format_without_leading_comments(&self.0.closing_bracket, config),
+                ]
+                .into(),
+                has_line_breaking_comments,
+            );
         }
 
-        string + "}"
+        let fields = self
+            .0
+            .iter()
+            .map(|item| (**item).format_with(config, is_type))
+            .collect::<Vec<_>>();
+
+        let layout = if has_line_breaking_comments
+            || has_magic_trailing_comma(self, config)
+            || !config.compact_table.should_be_single_line(self)
+        {
+            TableLayout::MultiLine
+        } else if config.compact_table == CompactTable::Always {
+            TableLayout::SingleLine
+        } else if fields.iter().any(Doc::has_broken_group) {
+            // Like the tables it has, which would otherwise be on its lines.
+            TableLayout::MultiLine
+        } else {
+            TableLayout::Fit
+        };
+
+        build_table(self, fields, layout, config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use luau_parser::parser::Parser;
+
+    use crate::{Config, format_with_config};
+
+    /// Formats the passed code with the passed config.
+    fn format_code(code: &str, config: &Config) -> String {
+        format_with_config(&Parser::new(code).parse(""), config).unwrap()
+    }
+
+    /// Checks that tables with a trailing comma keep a field per line with
+    /// `magic_trailing_comma`, and lose it when they fit on one line otherwise.
+    #[test]
+    fn magic_trailing_comma_keeps_tables_expanded() {
+        let code = "local t = { 1, 2, }\nlocal u = { a = 1, b = 2 }\nprint({ x, })\n";
+        let config = Config {
+            magic_trailing_comma: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format_code(code, &config),
+            "local t = {\n    1,\n    2,\n}\nlocal u = { a = 1, b = 2 }\nprint({\n    x,\n})\n"
+        );
+        assert_eq!(
+            format_code(code, &Config::default()),
+            "local t = { 1, 2 }\nlocal u = { a = 1, b = 2 }\nprint({ x })\n"
+        );
     }
 }
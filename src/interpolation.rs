@@ -0,0 +1,136 @@
+//! Splitting interpolated strings into their text and the code of the
+//! expressions between their braces, which luau-parser keeps as it is, to
+//! format and verify these expressions like any other.
+
+use luau_parser::{
+    parser::Parser,
+    prelude::Statement,
+    types::{Cst, Expression, ListItem, Pointer},
+};
+
+use crate::syntax_errors;
+
+/// A part of an interpolated string.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub(crate) enum Segment<'a> {
+    /// Text, as it is in the code, with its escape sequences and the backticks
+    /// around the string.
+    Text(&'a str),
+
+    /// The code of an expression, without the braces around it.
+    Expression(&'a str),
+}
+
+/// Gets the length of the long bracket starting the passed code, like `[==[`,
+/// and its level, if it starts with one.
+fn long_bracket(code: &[u8]) -> Option<(usize, usize)> {
+    let level = code.iter().skip(1).take_while(|byte| **byte == b'=').count();
+
+    (code.first() == Some(&b'[') && code.get(level + 1) == Some(&b'['))
+        .then_some((level + 2, level))
+}
+
+/// Finds the brace closing the expression the passed code starts with, skipping
+/// the strings and the tables in it.
+fn find_closing_brace(code: &str) -> Option<usize> {
+    let bytes = code.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'[' if long_bracket(&bytes[i..]).is_some() => {
+                let (length, level) = long_bracket(&bytes[i..])?;
+                let closing_bracket = format!("]{}]", "=".repeat(level));
+
+                i += length + code[i + length..].find(&closing_bracket)? + closing_bracket.len();
+                continue;
+            }
+            b'{' => depth += 1,
+            b'}' if depth == 0 => return Some(i),
+            b'}' => depth -= 1,
+            _ => (),
+        }
+
+        i += 1;
+    }
+
+    None
+}
+
+/// Splits the passed interpolated string, with its backticks, into its text and
+/// the expressions between its braces. An opening brace that isn't closed is
+/// kept as text.
+pub(crate) fn split_interpolated_string(string: &str) -> Vec<Segment<'_>> {
+    let bytes = string.as_bytes();
+    let mut segments = Vec::new();
+    let mut text_start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'{' => {
+                let Some(length) = find_closing_brace(&string[i + 1..]) else {
+                    break;
+                };
+
+                if text_start < i {
+                    segments.push(Segment::Text(&string[text_start..i]));
+                }
+                segments.push(Segment::Expression(&string[i + 1..i + 1 + length]));
+
+                i += length + 1;
+                text_start = i + 1;
+            }
+            _ => (),
+        }
+
+        i += 1;
+    }
+
+    if text_start < string.len() {
+        segments.push(Segment::Text(&string[text_start..]));
+    }
+
+    segments
+}
+
+/// Parses the code of an expression of an interpolated string, as the only
+/// expression of a `local` statement, which [`expression_of`] gets back. Code
+/// with comments isn't parsed, as they would comment out the rest of the
+/// string once formatted.
+pub(crate) fn parse_expression(code: &str) -> Option<Pointer<Cst>> {
+    if code.contains("--") {
+        return None;
+    }
+
+    let cst = Parser::new(&format!("local _ = {code}")).parse("");
+    (syntax_errors(&cst).is_empty() && expression_of(&cst).is_some()).then_some(cst)
+}
+
+/// Gets the expression parsed by [`parse_expression`].
+pub(crate) fn expression_of(cst: &Cst) -> Option<&Pointer<Expression>> {
+    let [(statement, None), (end_of_file, None)] = cst.block.statements.as_slice() else {
+        return None;
+    };
+    let (Statement::LocalAssignment(local_assignment), Statement::EndOfFile(_)) =
+        (&**statement, &**end_of_file)
+    else {
+        return None;
+    };
+    if cst.block.last_statement.is_some() {
+        return None;
+    }
+
+    match local_assignment.expressions.items.as_slice() {
+        [ListItem::NonTrailing(expression)] => Some(expression),
+        _ => None,
+    }
+}
@@ -0,0 +1,149 @@
+//! Finding the statements of a [`Block`] in the source code it was parsed
+//! from, for formatting only parts of it.
+
+use luau_parser::{
+    prelude::{Block, Statement, TerminationStatement, Token},
+    types::{Pointer, Print},
+};
+use std::ops::Range;
+
+use crate::formatter::get_statements;
+
+/// A statement of a [`Block`] along with the semicolon after it.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Item<'a> {
+    /// A [`Statement`].
+    Statement(&'a (Pointer<Statement>, Option<Token>)),
+
+    /// The [`TerminationStatement`] of the block.
+    Last(&'a (Pointer<TerminationStatement>, Option<Token>)),
+}
+
+impl<'a> Item<'a> {
+    /// The code of this item, with its leading trivia but without its trailing
+    /// trivia, which is also the leading trivia of the next item.
+    pub(crate) fn print(&self) -> String {
+        match self {
+            Item::Statement((statement, semicolon)) => {
+                statement.print_without_final_trivia() + &semicolon.print_without_final_trivia()
+            }
+            Item::Last((statement, semicolon)) => {
+                statement.print_without_final_trivia() + &semicolon.print_without_final_trivia()
+            }
+        }
+    }
+
+    /// The trailing trivia of this item.
+    pub(crate) fn print_final_trivia(&self) -> String {
+        match self {
+            Item::Statement((_, Some(semicolon))) | Item::Last((_, Some(semicolon))) => {
+                semicolon.print_final_trivia()
+            }
+            Item::Statement((statement, None)) => statement.print_final_trivia(),
+            Item::Last((statement, None)) => statement.print_final_trivia(),
+        }
+    }
+
+    /// The blocks nested directly in this item, like the body of a function.
+    pub(crate) fn nested_blocks(&self) -> Vec<&'a Block> {
+        let Item::Statement((statement, _)) = self else {
+            return Vec::new();
+        };
+
+        match &**statement {
+            Statement::LocalFunction(local_function) => vec![&local_function.body],
+            Statement::GlobalFunction(global_function) => vec![&global_function.body],
+            Statement::TypeFunction(type_function) => vec![&type_function.body],
+            Statement::IfStatement(if_statement) => [&if_statement.body]
+                .into_iter()
+                .chain(
+                    if_statement
+                        .else_if_statements
+                        .iter()
+                        .map(|else_if_statement| &else_if_statement.body),
+                )
+                .chain(
+                    if_statement
+                        .else_statement
+                        .as_ref()
+                        .map(|else_statement| &else_statement.body),
+                )
+                .collect(),
+            Statement::DoBlock(do_block) => vec![&do_block.body],
+            Statement::GenericFor(generic_for) => vec![&generic_for.do_block.body],
+            Statement::NumericalFor(numerical_for) => vec![&numerical_for.do_block.body],
+            Statement::WhileLoop(while_loop) => vec![&while_loop.do_block.body],
+            Statement::RepeatBlock(repeat_block) => vec![&repeat_block.body],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// An [`Item`] and where it is in the source code.
+#[derive(Clone, Debug)]
+pub(crate) struct LocatedItem<'a> {
+    /// The item.
+    pub(crate) item: Item<'a>,
+
+    /// The byte range of [`Item::print`] in the source code, if it was found.
+    pub(crate) span: Option<Range<usize>>,
+}
+
+impl LocatedItem<'_> {
+    /// The byte range of the code of this item, without the whitespace before
+    /// it. Comments before it are included as they're formatted with it.
+    pub(crate) fn code_span(&self, source: &str) -> Option<Range<usize>> {
+        let span = self.span.clone()?;
+        let text = &source[span.clone()];
+
+        Some(span.start + (text.len() - text.trim_start().len())..span.end)
+    }
+}
+
+/// Finds where each item of `block` is in `source`, starting the search at
+/// the byte offset `start`. Parts of the source between items that don't
+/// belong to any of them, which happens if the parser skipped some tokens, are
+/// returned as gaps.
+pub(crate) fn locate_items<'a>(
+    block: &'a Block,
+    source: &str,
+    start: usize,
+) -> (Vec<LocatedItem<'a>>, Vec<Range<usize>>) {
+    let items = get_statements(block)
+        .iter()
+        .map(Item::Statement)
+        .chain(block.last_statement.as_ref().map(Item::Last));
+
+    let mut located_items = Vec::new();
+    let mut gaps = Vec::new();
+    let mut cursor = start;
+
+    for item in items {
+        let text = item.print();
+        let span = source[cursor..].find(&text).map(|i| {
+            if i > 0 {
+                gaps.push(cursor..cursor + i);
+            }
+
+            cursor += i + text.len();
+            cursor - text.len()..cursor
+        });
+
+        located_items.push(LocatedItem { item, span });
+    }
+
+    (located_items, gaps)
+}
+
+/// Gets the end of the trailing comments of the passed item, which starts at
+/// `end`. They're formatted with the item, so they must be replaced with it.
+pub(crate) fn end_of_trailing_comments(item: &Item, source: &str, end: usize) -> usize {
+    let final_trivia = item.print_final_trivia();
+    let trailing_comments = final_trivia.trim_end();
+
+    if !trailing_comments.trim().is_empty() && source[end..].starts_with(trailing_comments) {
+        end + trailing_comments.len()
+    } else {
+        end
+    }
+}
@@ -5,24 +5,132 @@
 #![warn(missing_docs)]
 #![warn(clippy::absolute_paths)]
 
+pub use comments::LostComment;
 pub use config::*;
-use luau_parser::types::Cst;
-#[cfg(feature = "config-loading")]
-use std::{fs, io::Error as IoError, path::Path};
-#[cfg(feature = "config-loading")]
-use toml::de::Error as TomlError;
-use traits::Format;
+pub use dialect::{UnsupportedSyntax, unsupported_syntax};
+pub use edits::{TextEdit, apply_edits, compute_edits};
+pub use verify::{Divergence, verify};
+use luau_parser::{
+    prelude::{Error, Position, Statement},
+    types::{Cst, GetRange, Print},
+};
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    ops::Range,
+};
 
+mod comments;
 mod config;
+mod dialect;
+mod doc;
+mod edits;
 mod formatter;
+mod interpolation;
+mod items;
+mod range;
+mod resolver;
+mod tolerant;
 mod traits;
+mod verify;
 
 /// An error that may happen during formatting.
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum FormattingError {
     /// This [`Cst`] had a [`status`](luau_parser::types::AstStatus) of
     /// [`AstStatus::HasErrors`](luau_parser::types::AstStatus::HasErrors)
     ErroneousCst,
+
+    /// [`Config::verify`] is enabled and the formatted code doesn't mean the
+    /// same as the original code.
+    Divergence(Divergence),
+
+    /// A comment of the original code isn't in the formatted code.
+    LostComment(LostComment),
+
+    /// Formatting the formatted code again changes it.
+    NotIdempotent {
+        /// The code after formatting it once.
+        first_pass: String,
+
+        /// The code after formatting the first pass again.
+        second_pass: String,
+    },
+
+    /// The bindings the naming conventions of the [`Config`] rename couldn't
+    /// be renamed. Holds why.
+    RenamingFailed(String),
+}
+
+impl Display for FormattingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            FormattingError::ErroneousCst => write!(f, "the code has syntax errors"),
+            FormattingError::Divergence(divergence) => write!(
+                f,
+                "the formatted code doesn't mean the same as the original code: {divergence}"
+            ),
+            FormattingError::LostComment(lost_comment) => {
+                write!(f, "a comment was lost while formatting: {lost_comment}")
+            }
+            FormattingError::NotIdempotent { .. } => {
+                write!(f, "formatting the formatted code again changes it")
+            }
+            FormattingError::RenamingFailed(error) => {
+                write!(f, "the bindings couldn't be renamed: {error}")
+            }
+        }
+    }
+}
+
+impl StdError for FormattingError {}
+
+/// Checks that the formatted code has every comment of the original code, and
+/// that it means the same with [`verify`] if [`Config::verify`] is enabled.
+/// `renames` are the new names of the renamed identifiers, by their start in
+/// the original code.
+fn check_formatted_code(
+    cst: &Cst,
+    formatted_code: String,
+    renames: &HashMap<Position, String>,
+    config: &Config,
+) -> Result<String, FormattingError> {
+    if let Some(lost_comment) = comments::find_lost_comment(&cst.block.print(), &formatted_code)
+    {
+        return Err(FormattingError::LostComment(lost_comment));
+    }
+
+    if config.verify {
+        verify::verify_renamed(cst, &formatted_code, renames, config)
+            .map_err(FormattingError::Divergence)?;
+    }
+
+    Ok(formatted_code)
+}
+
+/// Gets the syntax errors of the passed [`Cst`], excluding the one luau-parser
+/// reports by mistake for the end of the file when the code ends with a `return`.
+/// That error is always the last one, as the end of the file is parsed last,
+/// and spans the end of the file. Other errors can be at the same position, so
+/// only the last one is dropped.
+pub fn syntax_errors(cst: &Cst) -> Vec<&Error> {
+    let end_of_file = cst.block.last_statement.as_ref().and(
+        match cst.block.statements.last().map(|(statement, _)| &**statement) {
+            Some(Statement::EndOfFile(end_of_file)) => end_of_file.get_range().ok(),
+            _ => None,
+        },
+    );
+
+    let mut errors = cst.errors.iter().collect::<Vec<_>>();
+    if let (Some(range), Some(error)) = (end_of_file, errors.last())
+        && error.start() == range.start
+        && error.end() == Some(range.end)
+    {
+        errors.pop();
+    }
+
+    errors
 }
 
 /// Formats the passed [`Cst`] with the default [`Config`].
@@ -34,45 +142,140 @@ pub fn format(cst: &Cst) -> Result<String, FormattingError> {
 /// Formats the passed [`Cst`] with the passed [`Config`].
 #[inline]
 pub fn format_with_config(cst: &Cst, config: &Config) -> Result<String, FormattingError> {
-    if cst.has_errors() {
+    if !syntax_errors(cst).is_empty() {
         Err(FormattingError::ErroneousCst)
     } else if cst.block.is_empty() {
         Ok("".to_string())
     } else {
-        Ok(cst.block.format(0, config))
+        let renames = resolver::find_renames(&cst.block, config);
+        let block = resolver::rename_bindings(&cst.block, &renames)
+            .map_err(|error| FormattingError::RenamingFailed(error.to_string()))?;
+
+        check_formatted_code(
+            cst,
+            formatter::format_block(&block, 0, config),
+            &renames,
+            config,
+        )
     }
 }
 
-/// Errors that may happen during loading of a [`Config`] from a `.toml` file.
-#[cfg(feature = "config-loading")]
-pub enum LoadConfigError {
-    /// An [`io error`](IoError).
-    Io(IoError),
+/// Formats the passed [`Cst`] with the passed [`Config`], then formats the
+/// formatted code again and checks that it didn't change. Returns the formatted
+/// code if it didn't, and both passes otherwise.
+pub fn check_idempotency(cst: &Cst, config: &Config) -> Result<String, FormattingError> {
+    let first_pass = format_with_config(cst, config)?;
+    let formatted_cst =
+        verify::parse_formatted_code(&first_pass).map_err(FormattingError::Divergence)?;
+    let second_pass = format_with_config(&formatted_cst, config)?;
 
-    /// An error parsing the TOML.
-    Toml(TomlError),
+    if first_pass == second_pass {
+        Ok(first_pass)
+    } else {
+        Err(FormattingError::NotIdempotent {
+            first_pass,
+            second_pass,
+        })
+    }
 }
 
-#[cfg(feature = "config-loading")]
-impl From<IoError> for LoadConfigError {
-    #[inline]
-    fn from(value: IoError) -> Self {
-        Self::Io(value)
+/// Formats the passed [`Cst`] with the passed [`Config`], and returns the
+/// minimal edits that turn the code it was parsed from into the formatted code.
+/// The ranges of the edits are byte ranges of that code. Applying them with
+/// [`apply_edits`] gives the same result as [`format_with_config`].
+pub fn format_edits(cst: &Cst, config: &Config) -> Result<Vec<TextEdit>, FormattingError> {
+    let formatted_code = format_with_config(cst, config)?;
+
+    Ok(compute_edits(&cst.block.print(), &formatted_code))
+}
+
+/// Formats the passed [`Cst`] with the passed [`Config`], even if it has syntax
+/// errors. Statements that have errors, or that are on the same line as one,
+/// are kept exactly as they're in `source`, which must be the code the [`Cst`]
+/// was parsed from, except for the statements in their bodies, which are
+/// formatted like the rest if they're far enough from the errors. Everything
+/// else is formatted, without renaming bindings, as the kept statements may
+/// use them. `source` is returned as-is if formatting it fails, like when a
+/// comment would be lost.
+pub fn format_tolerant(cst: &Cst, source: &str, config: &Config) -> String {
+    let errors = syntax_errors(cst);
+
+    if errors.is_empty() {
+        return format_with_config(cst, config).unwrap_or_else(|_| source.to_string());
     }
+
+    tolerant::format_tolerant(&cst.block, source, &config.without_renaming(), &errors)
 }
-#[cfg(feature = "config-loading")]
-impl From<TomlError> for LoadConfigError {
-    #[inline]
-    fn from(value: TomlError) -> Self {
-        Self::Toml(value)
+
+/// Formats only the statements of the passed [`Cst`] that intersect `range`, a
+/// byte range of the code, with the passed [`Config`]. Everything else is kept
+/// exactly as it is. If `range` is inside the body of a statement, like a
+/// function, only the statements of that body that intersect it are formatted,
+/// at the indentation of that body. An empty range formats the statement it's
+/// in. Parts of `range` outside the code are ignored. Bindings aren't renamed,
+/// as the code outside `range` may use them.
+pub fn format_range(
+    cst: &Cst,
+    config: &Config,
+    range: Range<usize>,
+) -> Result<String, FormattingError> {
+    if !syntax_errors(cst).is_empty() {
+        return Err(FormattingError::ErroneousCst);
     }
+
+    let mut source = cst.block.print();
+
+    // Make sure the range is inside the code and doesn't split characters.
+    let clamp = |mut offset: usize| {
+        offset = offset.min(source.len());
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    };
+    let start = clamp(range.start);
+    let range = start..clamp(range.end).max(start);
+
+    if let Some((replaced_range, formatted_code)) =
+        range::format_block_range(&cst.block, &source, 0, 0, config, range)
+    {
+        source.replace_range(replaced_range, &formatted_code);
+    }
+
+    check_formatted_code(cst, source, &HashMap::new(), config)
 }
 
-/// Load a [`Config`] from the passed path.
-#[inline]
-#[cfg(feature = "config-loading")]
-pub fn load_config<P: AsRef<Path>>(path: P) -> Result<Config, LoadConfigError> {
-    fs::read_to_string(path)
-        .map(|content| toml::from_str(&content))?
-        .map_err(From::from)
+#[cfg(test)]
+mod tests {
+    use luau_parser::parser::Parser;
+
+    use super::syntax_errors;
+
+    /// [`syntax_errors`] works around luau-parser reporting an error for the
+    /// end of the file after a `return`. This fails once it stops doing so, as
+    /// the workaround would then hide real errors at the end of the file.
+    #[test]
+    fn end_of_file_is_reported_after_return() {
+        let cst = Parser::new("return 1\n").parse("");
+
+        assert_eq!(cst.errors.len(), 1);
+        assert!(syntax_errors(&cst).is_empty());
+    }
+
+    #[test]
+    fn errors_at_the_end_of_file_are_kept() {
+        for code in ["return 1 +", "return (1", "return 1\nlocal x = 1\n"] {
+            let cst = Parser::new(code).parse("");
+
+            assert_eq!(syntax_errors(&cst).len(), cst.errors.len() - 1, "{code:?}");
+            assert!(!syntax_errors(&cst).is_empty(), "{code:?}");
+        }
+    }
+
+    #[test]
+    fn nested_returns_have_no_errors() {
+        let cst = Parser::new("if x then return 1 end\nreturn 2").parse("");
+
+        assert!(syntax_errors(&cst).is_empty());
+    }
 }
@@ -1,72 +1,603 @@
-use clap::Parser as ClapParser;
-use luau_fmt::{Config, format_with_config, load_config};
-use luau_parser::parser::Parser as LuauParser;
+use clap::{Parser as ClapParser, Subcommand};
+use cli::{
+    config::{ConfigError, ConfigResolver},
+    diff::{ColorChoice, unified_diff},
+    files::{FileFilter, FilesConfig},
+    jobs::{default_jobs, run_parallel},
+    lsp,
+    range::FormatRange,
+};
+use luau_fmt::{
+    Config, Divergence, FormattingError, LostComment, check_idempotency, format_range,
+    format_tolerant, format_with_config, syntax_errors, unsupported_syntax,
+};
+use luau_parser::{parser::Parser as LuauParser, prelude::Error as SyntaxError};
 use std::{
-    fs, io,
+    fs,
+    io::{self, Write},
+    num::NonZeroUsize,
     path::{Path, PathBuf},
+    process::ExitCode,
 };
 
+mod cli;
+
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-#[inline]
-fn format_folder(path: &Path, config: &Config) -> io::Result<()> {
-    if path.file_name().is_some_and(|name| name == ".git") {
-        return Ok(());
-    }
+/// The path that makes `luaufmt` read from stdin.
+const STDIN_PATH: &str = "-";
 
-    for file in path.read_dir()? {
-        format_path(file?.path(), config)?;
-    }
+/// The name used for code read from stdin if `--stdin-filepath` isn't passed.
+const STDIN_NAME: &str = "<stdin>";
+
+/// What to do with the formatted code of each file.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+enum Mode {
+    /// Write the formatted code back to the file.
+    #[default]
+    Write,
+
+    /// Don't write anything, only report the files that would change.
+    Check,
+
+    /// Don't write anything, print a diff of the files that would change.
+    Diff {
+        /// Whether or not to colorize the diff.
+        colorize: bool,
+    },
+}
+
+/// State shared between all formatted files.
+struct Context {
+    /// Finds the config to format each file with.
+    configs: ConfigResolver,
+
+    /// What to do with the formatted code.
+    mode: Mode,
+
+    /// Whether or not to format the code around syntax errors instead of
+    /// skipping files that have them.
+    tolerant: bool,
+
+    /// The only part of the file to format, if any.
+    range: Option<FormatRange>,
+
+    /// Whether or not to check that the formatted code means the same as the
+    /// original code, for all files.
+    verify: bool,
+
+    /// Whether or not to format the formatted code again and report files
+    /// where it changes.
+    check_idempotency: bool,
+
+    /// Decides which files get formatted.
+    filter: FileFilter,
+}
+
+/// The result of formatting a single file.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+enum Outcome {
+    /// The file is already formatted, or was skipped.
+    Unchanged,
+
+    /// The file has syntax errors and was skipped.
+    SyntaxErrors(Vec<SyntaxError>),
+
+    /// The formatted code doesn't mean the same as the original code, so it
+    /// wasn't written.
+    Diverged(Divergence),
+
+    /// A comment of the original code isn't in the formatted code, so it
+    /// wasn't written.
+    LostComment(LostComment),
+
+    /// Formatting the formatted code again changes it, so it wasn't written.
+    NotIdempotent {
+        /// The code after formatting it once.
+        first_pass: String,
+
+        /// The code after formatting the first pass again.
+        second_pass: String,
+    },
+
+    /// The file was reformatted, or would be if not in [`Mode::Write`]. Holds
+    /// the diff in [`Mode::Diff`].
+    Changed(Option<String>),
+}
 
-    Ok(())
+/// What formatting a single file reported.
+#[derive(Debug)]
+struct Report {
+    /// Messages to print before the outcome, like warnings about syntax the
+    /// target doesn't support. They're printed in the order of the files
+    /// instead of from the threads formatting them, so the output doesn't
+    /// change between runs.
+    messages: Vec<String>,
+
+    /// The result of formatting the file.
+    outcome: io::Result<Outcome>,
 }
 
-fn format_file(path: &Path, config: &Config) -> io::Result<()> {
-    if !path
-        .extension()
+/// Whether or not the passed file is a Lua or a Luau file.
+#[inline]
+fn is_luau_file(path: &Path) -> bool {
+    path.extension()
         .is_some_and(|extension| extension == "lua" || extension == "luau")
-    {
-        return Ok(());
+}
+
+/// Gets all the files to format in the passed folder, sorted by path.
+fn collect_files(path: &Path, context: &Context) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    for entry in context.filter.walk(path) {
+        match entry {
+            Ok(entry)
+                if entry.file_type().is_some_and(|file_type| file_type.is_file())
+                    && is_luau_file(entry.path()) =>
+            {
+                files.push(entry.into_path())
+            }
+            Ok(_) => (),
+            Err(error) => eprintln!("{error}"),
+        }
     }
 
-    let content = fs::read_to_string(path)?;
-    let mut parser = LuauParser::new(&content);
+    files
+}
+
+/// Formats `content` with the passed config. `write` is called with the
+/// formatted code in [`Mode::Write`], otherwise it's compared to `content`.
+/// Messages to print are pushed to `messages`.
+fn format_content<F>(
+    path: &Path,
+    content: &str,
+    config: &Config,
+    context: &Context,
+    messages: &mut Vec<String>,
+    write: F,
+) -> io::Result<Outcome>
+where
+    F: FnOnce(String) -> io::Result<Outcome>,
+{
+    let mut parser = LuauParser::new(content);
     let cst = parser.parse(path.to_string_lossy().as_ref());
 
-    if let Ok(code) = format_with_config(&cst, config) {
-        fs::write(path, code.as_bytes())?;
-    } else {
-        eprintln!("{:?} has syntax errors. Skipping.", path);
+    let config = &Config {
+        verify: config.verify || context.verify,
+        ..config.clone()
+    };
+
+    for unsupported_syntax in unsupported_syntax(&cst, config) {
+        messages.push(format!("warning: {}: {unsupported_syntax}", path.display()));
     }
 
-    Ok(())
+    let formatted_code = match &context.range {
+        Some(range) => format_range(&cst, config, range.to_byte_range(content)),
+        None if context.check_idempotency => check_idempotency(&cst, config),
+        None => format_with_config(&cst, config),
+    };
+
+    let code = match formatted_code {
+        Ok(code) => code,
+        Err(FormattingError::Divergence(divergence)) => {
+            return Ok(Outcome::Diverged(divergence));
+        }
+        Err(FormattingError::LostComment(lost_comment)) => {
+            return Ok(Outcome::LostComment(lost_comment));
+        }
+        Err(FormattingError::NotIdempotent {
+            first_pass,
+            second_pass,
+        }) => {
+            return Ok(Outcome::NotIdempotent {
+                first_pass,
+                second_pass,
+            });
+        }
+        Err(error @ FormattingError::RenamingFailed(_)) => {
+            return Err(io::Error::other(error.to_string()));
+        }
+        Err(FormattingError::ErroneousCst) if context.tolerant => {
+            messages.push(format!(
+                "{:?} has syntax errors. Only formatting the code around them.",
+                path
+            ));
+
+            format_tolerant(&cst, content, config)
+        }
+        Err(FormattingError::ErroneousCst) => {
+            return Ok(Outcome::SyntaxErrors(
+                syntax_errors(&cst).into_iter().cloned().collect(),
+            ));
+        }
+    };
+
+    match context.mode {
+        Mode::Write => write(code),
+        _ if code == content => Ok(Outcome::Unchanged),
+        Mode::Check => Ok(Outcome::Changed(None)),
+        Mode::Diff { colorize } => Ok(Outcome::Changed(Some(unified_diff(
+            path, content, &code, colorize,
+        )))),
+    }
 }
 
-#[inline]
-pub fn format_path<P: AsRef<Path>>(path: P, config: &Config) -> io::Result<()> {
-    let path = path.as_ref();
+fn format_file(path: &Path, config: &Config, context: &Context) -> Report {
+    let mut messages = Vec::new();
 
-    if path.is_dir() {
-        format_folder(path, config)
+    let outcome = if is_luau_file(path) {
+        fs::read_to_string(path).and_then(|content| {
+            format_content(path, &content, config, context, &mut messages, |code| {
+                if code == content {
+                    Ok(Outcome::Unchanged)
+                } else {
+                    fs::write(path, code.as_bytes())?;
+                    Ok(Outcome::Changed(None))
+                }
+            })
+        })
     } else {
-        format_file(path, config)
-    }
+        Ok(Outcome::Unchanged)
+    };
+
+    Report { messages, outcome }
+}
+
+/// Formats the code read from stdin and writes it to stdout. `path` is only
+/// used to identify the code, to find its config file and to check if it's
+/// ignored, in which case it's written back as-is.
+fn format_stdin(path: &Path, context: &Context) -> Result<Report, ConfigError> {
+    let config = context.configs.config_for(path)?;
+    let mut messages = Vec::new();
+
+    let outcome = io::read_to_string(io::stdin()).and_then(|content| {
+        if context.filter.is_ignored(path) {
+            if context.mode == Mode::Write {
+                io::stdout().write_all(content.as_bytes())?;
+            }
+
+            return Ok(Outcome::Unchanged);
+        }
+
+        format_content(path, &content, &config, context, &mut messages, |code| {
+            io::stdout().write_all(code.as_bytes())?;
+            Ok(Outcome::Unchanged)
+        })
+    });
+
+    Ok(Report { messages, outcome })
+}
+
+/// Formats the passed file, or all files in the passed folder, using `jobs`
+/// threads. Returns each file with its report, sorted by path. The configs of
+/// all files are loaded first, so nothing is written if any of them is invalid.
+fn format_path<P: AsRef<Path>>(
+    path: P,
+    context: &Context,
+    jobs: NonZeroUsize,
+) -> Result<Vec<(PathBuf, Report)>, ConfigError> {
+    let path = path.as_ref();
+    let files = if path.is_dir() {
+        collect_files(path, context)
+    } else if context.filter.is_ignored(path) {
+        Vec::new()
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let files = files
+        .into_iter()
+        .map(|file| Ok((context.configs.config_for(&file)?, file)))
+        .collect::<Result<Vec<(Config, PathBuf)>, ConfigError>>()?;
+
+    let reports = run_parallel(&files, jobs, |(config, file)| {
+        format_file(file, config, context)
+    });
+
+    Ok(files
+        .into_iter()
+        .map(|(_, file)| file)
+        .zip(reports)
+        .collect())
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a language server over stdio, supporting document and range
+    /// formatting.
+    Lsp,
 }
 
 #[derive(ClapParser)]
-#[command(version = VERSION)]
+#[command(
+    version = VERSION,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Cli {
-    path: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The file or folder to format. Pass `-` to read from stdin and write to
+    /// stdout.
+    #[arg(required = true)]
+    path: Option<PathBuf>,
+
+    /// The config file to use for all files. If not passed, each file uses the
+    /// closest `luaufmt.toml` or `.luaufmt.toml`, starting from its folder.
     config_path: Option<PathBuf>,
+
+    /// The path of the code read from stdin. It's used to identify the code in
+    /// messages, to find its config file and to check if it's ignored.
+    #[arg(long, value_name = "PATH")]
+    stdin_filepath: Option<PathBuf>,
+
+    /// Only format files matching this glob. Can be passed multiple times.
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Don't format files matching this glob. Can be passed multiple times.
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Don't write the files, only list the ones that aren't formatted and exit
+    /// with a non-zero status if there are any.
+    #[arg(long)]
+    check: bool,
+
+    /// Don't write the files, print a unified diff of the changes that would be
+    /// made and exit with a non-zero status if there are any.
+    #[arg(long, conflicts_with = "check")]
+    diff: bool,
+
+    /// When to colorize the output of `--diff`.
+    #[arg(long, value_enum, default_value_t)]
+    color: ColorChoice,
+
+    /// Format the code around syntax errors instead of skipping files that
+    /// have them. Statements with errors are kept as they are, apart from
+    /// their bodies.
+    #[arg(long)]
+    tolerant: bool,
+
+    /// Only format the statements intersecting this byte range, where END is
+    /// exclusive. Only works with a single file.
+    #[arg(
+        long,
+        value_name = "START:END",
+        value_parser = FormatRange::parse_bytes,
+        conflicts_with = "tolerant"
+    )]
+    range: Option<FormatRange>,
+
+    /// Only format the statements intersecting these lines, starting from 1,
+    /// where LAST is inclusive. Only works with a single file.
+    #[arg(
+        long,
+        value_name = "FIRST-LAST",
+        value_parser = FormatRange::parse_lines,
+        conflicts_with_all = ["tolerant", "range"]
+    )]
+    lines: Option<FormatRange>,
+
+    /// Parse the formatted code again and check that it means the same as the
+    /// original code. Files where it doesn't aren't written, and the first
+    /// node that differs is reported.
+    #[arg(long)]
+    verify: bool,
+
+    /// Format the formatted code again and report files where it changes, with
+    /// the diff between both passes. These files aren't written.
+    #[arg(long, conflicts_with_all = ["tolerant", "range", "lines"])]
+    check_idempotency: bool,
+
+    /// Number of files to format at the same time. Defaults to the number of
+    /// available cores.
+    #[arg(long, short, value_name = "N")]
+    jobs: Option<NonZeroUsize>,
 }
 
-fn main() -> io::Result<()> {
+fn main() -> io::Result<ExitCode> {
     let args = Cli::parse();
-    let config = if let Some(path) = &args.config_path {
-        load_config(path).unwrap_or_default()
+    let path = match (args.command, args.path) {
+        (Some(Command::Lsp), _) => {
+            return Ok(match lsp::run() {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(error) => {
+                    eprintln!("error: {error}");
+                    ExitCode::FAILURE
+                }
+            });
+        }
+        (None, Some(path)) => path,
+        (None, None) => unreachable!("clap requires the path without a subcommand"),
+    };
+    let is_stdin = path == Path::new(STDIN_PATH);
+    let range = args.range.or(args.lines);
+
+    if !is_stdin && !path.exists() {
+        eprintln!("error: {} doesn't exist", path.display());
+        return Ok(ExitCode::FAILURE);
+    }
+    if range.is_some() && path.is_dir() {
+        eprintln!("error: `--range` and `--lines` can only be used with a single file");
+        return Ok(ExitCode::FAILURE);
+    }
+    let configs = ConfigResolver::new(args.config_path);
+
+    let root_path = if is_stdin {
+        args.stdin_filepath
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(STDIN_NAME))
     } else {
-        load_config(PathBuf::from("luaufmt.toml")).unwrap_or_default()
+        path.clone()
+    };
+    let config_path = configs.find_config_path(&root_path);
+    let files_config = config_path
+        .as_ref()
+        .map(|config_path| {
+            FilesConfig::load(config_path).map_err(|error| ConfigError {
+                path: config_path.clone(),
+                error,
+            })
+        })
+        .transpose();
+    let files_config = match files_config {
+        Ok(files_config) => files_config.unwrap_or_default(),
+        Err(error) => {
+            eprintln!("error: {error}");
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+
+    let filter = FileFilter::new(
+        &files_config,
+        config_path.as_deref(),
+        &args.include,
+        &args.exclude,
+    );
+    let filter = match filter {
+        Ok(filter) => filter,
+        Err(error) => {
+            eprintln!("Invalid glob: {error}");
+            return Ok(ExitCode::FAILURE);
+        }
     };
 
-    format_path(&args.path, &config)
+    let context = Context {
+        configs,
+        mode: if args.diff {
+            Mode::Diff {
+                colorize: args.color.should_colorize(),
+            }
+        } else if args.check {
+            Mode::Check
+        } else {
+            Mode::Write
+        },
+        tolerant: args.tolerant,
+        range,
+        verify: args.verify,
+        check_idempotency: args.check_idempotency,
+        filter,
+    };
+
+    let reports = if is_stdin {
+        let path = root_path;
+
+        let report = match format_stdin(&path, &context) {
+            Ok(report) => report,
+            Err(error) => {
+                eprintln!("error: {error}");
+                return Ok(ExitCode::FAILURE);
+            }
+        };
+
+        match report.outcome {
+            Ok(Outcome::SyntaxErrors(errors)) => {
+                for message in report.messages {
+                    eprintln!("{message}");
+                }
+                for error in errors.iter() {
+                    let start = error.start();
+
+                    eprintln!(
+                        "{}:{}:{}: {}",
+                        path.display(),
+                        start.line + 1,
+                        start.character + 1,
+                        error.message()
+                    );
+                }
+
+                return Ok(ExitCode::FAILURE);
+            }
+            outcome => vec![(
+                path,
+                Report {
+                    messages: report.messages,
+                    outcome,
+                },
+            )],
+        }
+    } else {
+        match format_path(&path, &context, args.jobs.unwrap_or_else(default_jobs)) {
+            Ok(reports) => reports,
+            Err(error) => {
+                eprintln!("error: {error}");
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    };
+
+    let mut unformatted_files = 0;
+    let mut had_errors = false;
+
+    for (path, report) in reports {
+        for message in report.messages {
+            eprintln!("{message}");
+        }
+
+        match report.outcome {
+            Ok(Outcome::Unchanged) => (),
+            Ok(Outcome::SyntaxErrors(_)) => {
+                eprintln!("{:?} has syntax errors. Skipping.", path);
+                had_errors = true;
+            }
+            Ok(Outcome::Diverged(divergence)) => {
+                eprintln!(
+                    "{}: the formatted code doesn't mean the same as the original code, so it \
+                     wasn't written: {divergence}",
+                    path.display()
+                );
+                had_errors = true;
+            }
+            Ok(Outcome::LostComment(lost_comment)) => {
+                eprintln!(
+                    "{}: formatting would lose a comment, so it wasn't written: {lost_comment}",
+                    path.display()
+                );
+                had_errors = true;
+            }
+            Ok(Outcome::NotIdempotent {
+                first_pass,
+                second_pass,
+            }) => {
+                eprintln!(
+                    "{}: formatting the formatted code again changes it, so it wasn't written:",
+                    path.display()
+                );
+                eprint!(
+                    "{}",
+                    unified_diff(&path, &first_pass, &second_pass, args.color.should_colorize())
+                );
+                had_errors = true;
+            }
+            Ok(Outcome::Changed(diff)) => {
+                match context.mode {
+                    Mode::Write => (),
+                    Mode::Check => println!("{}", path.display()),
+                    Mode::Diff { .. } => print!("{}", diff.unwrap_or_default()),
+                }
+
+                unformatted_files += 1;
+            }
+            Err(error) => {
+                eprintln!("{}: {error}", path.display());
+                had_errors = true;
+            }
+        }
+    }
+
+    if context.mode != Mode::Write && unformatted_files > 0 {
+        eprintln!("{unformatted_files} file(s) would be reformatted.");
+
+        return Ok(ExitCode::FAILURE);
+    }
+
+    Ok(if had_errors {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
 }
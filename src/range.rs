@@ -0,0 +1,154 @@
+//! Formatting only the statements that intersect a range of the source code,
+//! and keeping everything else as-is.
+
+use luau_parser::prelude::Block;
+use std::ops::Range;
+
+use crate::{
+    config::Config,
+    formatter::format_block,
+    items::{Item, LocatedItem, end_of_trailing_comments, locate_items},
+    traits::Indentation,
+};
+
+/// The byte range of the code of each passed item in the source code, from
+/// the start of its leading comments to the end of its trailing ones. Leading
+/// comments that are trailing comments of the previous item are left out.
+fn code_spans(located_items: &[LocatedItem], source: &str) -> Option<Vec<Range<usize>>> {
+    let mut spans: Vec<Range<usize>> = Vec::new();
+
+    for located_item in located_items {
+        let mut start = located_item.code_span(source)?.start;
+        let end = located_item.span.as_ref()?.end;
+
+        if let Some(previous_span) = spans.last() {
+            // The previous item's trailing comments can cover all of this
+            // item's code if it's only comments, like the end of the file.
+            let text = &source[start.max(previous_span.end).min(end)..end];
+            start = end - text.trim_start().len();
+        }
+
+        spans.push(start..end_of_trailing_comments(&located_item.item, source, end));
+    }
+
+    Some(spans)
+}
+
+/// Finds the statements of the passed [`Block`], searching from `start`.
+/// Returns where the search for them starts, to pass it to [`locate_items`]
+/// again, and the byte range spanned by their code.
+fn block_span(block: &Block, source: &str, start: usize) -> Option<(usize, Range<usize>)> {
+    let (located_items, _) = locate_items(block, source, start);
+    let spans = code_spans(&located_items, source)?;
+
+    Some((
+        located_items.first()?.span.as_ref()?.start,
+        spans.first()?.start..spans.last()?.end,
+    ))
+}
+
+/// Formats the statements of the passed [`Block`], which starts being searched
+/// for at `start`, that intersect `range`. If `range` is inside the body of a
+/// single statement, only the statements of that body are formatted.
+///
+/// Returns the byte range to replace in the source code and the formatted
+/// code to replace it with, or [`None`] if no statement intersects `range`.
+pub(crate) fn format_block_range(
+    block: &Block,
+    source: &str,
+    start: usize,
+    indentation: Indentation,
+    config: &Config,
+    range: Range<usize>,
+) -> Option<(Range<usize>, String)> {
+    // Whitespace around the range doesn't belong to any statement, like the
+    // indentation of the first line of a line range.
+    let text = &source[range.clone()];
+    let range = if text.trim().is_empty() {
+        range
+    } else {
+        let start = range.start + (text.len() - text.trim_start().len());
+        start..start + text.trim().len()
+    };
+
+    // An empty range is a cursor, which formats the statement it's in.
+    let range_end = range.end.max(range.start + 1);
+
+    let (located_items, _) = locate_items(block, source, start);
+    let spans = code_spans(&located_items, source)?;
+
+    let first = spans
+        .iter()
+        .position(|span| span.start < range_end && range.start < span.end)?;
+    let last = spans
+        .iter()
+        .rposition(|span| span.start < range_end && range.start < span.end)?;
+
+    if first == last {
+        let mut cursor = spans[first].start;
+
+        for nested_block in located_items[first].item.nested_blocks() {
+            let Some((nested_start, nested_span)) = block_span(nested_block, source, cursor)
+            else {
+                continue;
+            };
+
+            if nested_span.start <= range.start && range_end <= nested_span.end {
+                return format_block_range(
+                    nested_block,
+                    source,
+                    nested_start,
+                    indentation + 1,
+                    config,
+                    range,
+                );
+            }
+
+            cursor = nested_span.end;
+        }
+    }
+
+    let mut statements = Vec::new();
+    let mut last_statement = None;
+    for located_item in &located_items[first..=last] {
+        match located_item.item {
+            Item::Statement(statement) => statements.push(statement.clone()),
+            Item::Last(statement) => last_statement = Some(statement.clone()),
+        }
+    }
+
+    let formatted_code = format_block(
+        &Block {
+            statements,
+            last_statement,
+        },
+        indentation,
+        config,
+    );
+    let mut formatted_code = formatted_code.trim();
+
+    // The trailing comments of the previous statement are the leading ones of
+    // the first formatted statement, so they're formatted too, but they must
+    // stay where they are.
+    if first > 0 {
+        let previous_end = located_items[first - 1].span.as_ref()?.end;
+        let trailing_comments = source[previous_end..spans[first - 1].end].trim();
+
+        if let Some(code) = formatted_code.strip_prefix(trailing_comments) {
+            formatted_code = code.trim_start();
+        }
+    }
+
+    let start = spans[first].start;
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+
+    // Fix the indentation of the first line too if nothing is before it.
+    if source[line_start..start].trim().is_empty() {
+        Some((
+            line_start..spans[last].end,
+            config.indent_style.to_string(indentation, config) + formatted_code,
+        ))
+    } else {
+        Some((start..spans[last].end, formatted_code.to_string()))
+    }
+}
@@ -0,0 +1,878 @@
+//! Resolving the identifiers of a [`Block`] to the local bindings they refer
+//! to, to rename these bindings, and only them, with the naming conventions of
+//! the config. Globals, table fields, methods and exported types are never
+//! renamed, as other files can use them.
+
+use luau_parser::{
+    prelude::{Position, Token, TokenType},
+    types::{
+        Block, BracketedList, Expression, FunctionArgument, FunctionArguments, FunctionCall,
+        FunctionCallInvoked, GenericDeclaration, GenericParameterInfo,
+        GenericParameterInfoDefault, GlobalFunctionName, Parameter, ParameterTypeName, Pointer,
+        PrefixExp, Statement, Table, TableAccessKey, TableAccessPrefix, TableFieldValue,
+        TableKey, TerminationStatement, TypeValue, Var,
+    },
+};
+use serde_json::{Error as JsonError, Value};
+use std::{borrow::Cow, collections::HashMap};
+
+use crate::{
+    config::{Config, NamingConvention},
+    interpolation::{Segment, expression_of, parse_expression, split_interpolated_string},
+    verify::serialize,
+};
+
+/// Names bindings can't be renamed to, as they're keywords, or contextual
+/// keywords that can't be used as names everywhere.
+const KEYWORDS: [&str; 25] = [
+    "and", "break", "continue", "do", "else", "elseif", "end", "export", "false", "for",
+    "function", "if", "in", "local", "nil", "not", "or", "repeat", "return", "then", "true",
+    "type", "typeof", "until", "while",
+];
+
+/// Globals of Luau and Roblox. Bindings named like them shadow them on purpose,
+/// so they're kept, and no binding is renamed to them.
+const GLOBALS: [&str; 81] = [
+    "_G", "_VERSION", "assert", "bit32", "buffer", "collectgarbage", "coroutine", "debug",
+    "error", "gcinfo", "getfenv", "getmetatable", "ipairs", "loadstring", "math", "newproxy",
+    "next", "os", "pairs", "pcall", "print", "rawequal", "rawget", "rawlen", "rawset", "require",
+    "select", "setfenv", "setmetatable", "string", "table", "tonumber", "tostring", "type",
+    "typeof", "unpack", "utf8", "vector", "xpcall", "Axes", "BrickColor", "CFrame",
+    "CatalogSearchParams", "Color3", "ColorSequence", "ColorSequenceKeypoint", "DateTime",
+    "DockWidgetPluginGuiInfo", "Enum", "Faces", "Font", "Game", "Instance", "NumberRange",
+    "NumberSequence", "NumberSequenceKeypoint", "OverlapParams", "PathWaypoint",
+    "PhysicalProperties", "Random", "Ray", "RaycastParams", "Rect", "Region3", "SharedTable",
+    "TweenInfo", "UDim", "UDim2", "UserSettings", "Vector2", "Vector3", "Workspace", "delay",
+    "game", "plugin", "script", "shared", "spawn", "task", "tick", "workspace",
+];
+
+/// Types built into Luau. Type bindings named like them are kept, and no type
+/// binding is renamed to them.
+const BUILTIN_TYPES: [&str; 12] = [
+    "any", "boolean", "buffer", "never", "nil", "number", "string", "table", "thread",
+    "unknown", "userdata", "vector",
+];
+
+/// What a binding names, which picks the naming convention it's renamed with.
+/// Values and types are in different namespaces.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+enum BindingKind {
+    /// A local variable or function, or a parameter.
+    Variable,
+
+    /// A local type or type function, or a generic type parameter.
+    Type,
+}
+
+impl BindingKind {
+    /// The naming convention the config asks for bindings of this kind.
+    fn naming_convention(self, config: &Config) -> NamingConvention {
+        match self {
+            Self::Variable => config.variable_casing,
+            Self::Type => config.type_casing,
+        }
+    }
+
+    /// The names of bindings of this kind that the config asks to never rename.
+    fn allow_list(self, config: &Config) -> &[String] {
+        match self {
+            Self::Variable => &config.variable_casing_allow_list,
+            Self::Type => &config.type_casing_allow_list,
+        }
+    }
+
+    /// Whether or not bindings of this kind named `name` must be kept as they
+    /// are, and bindings can't be renamed to it.
+    fn is_reserved(self, name: &str) -> bool {
+        KEYWORDS.contains(&name)
+            || match self {
+                Self::Variable => GLOBALS.contains(&name),
+                Self::Type => BUILTIN_TYPES.contains(&name),
+            }
+    }
+}
+
+/// A local binding.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct Binding {
+    /// Its name.
+    name: String,
+
+    /// What it names.
+    kind: BindingKind,
+
+    /// Whether or not it can be renamed, which it can't if it's exported, named
+    /// like a global, or referred to in an interpolated string.
+    is_renamable: bool,
+
+    /// The start of the identifiers naming it, in its declaration and in its
+    /// references.
+    positions: Vec<Position>,
+}
+
+/// An identifier that isn't a declaration.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct Reference {
+    /// Its name.
+    name: String,
+
+    /// The binding it refers to, which is a global if there's none.
+    binding: Option<usize>,
+}
+
+/// The names identifiers are resolved with instead of their own, to check that
+/// each of them refers to the same binding once bindings are renamed.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+struct Renamed<'a> {
+    /// The name of each binding, in the order they're declared.
+    bindings: &'a [String],
+
+    /// The name of each reference, in the order they're in the code.
+    references: &'a [String],
+}
+
+/// Links the identifiers of a [`Block`] to the bindings they refer to, while
+/// walking it in the order of the code.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+struct Resolver<'a> {
+    /// The declared bindings.
+    bindings: Vec<Binding>,
+
+    /// The walked references.
+    references: Vec<Reference>,
+
+    /// The bindings declared in each scope, from the outermost one.
+    scopes: Vec<Vec<usize>>,
+
+    /// The names to resolve identifiers with, if bindings are renamed.
+    renamed: Option<Renamed<'a>>,
+
+    /// Whether or not the walked expression is in an interpolated string, where
+    /// identifiers can't be renamed as the string is kept as it is.
+    is_in_interpolated_string: bool,
+}
+
+impl Resolver<'_> {
+    /// Declares a binding in the current scope.
+    fn push_binding(
+        &mut self,
+        name: &str,
+        kind: BindingKind,
+        is_renamable: bool,
+        position: Option<Position>,
+    ) {
+        let index = self.bindings.len();
+        let name = match self.renamed {
+            Some(renamed) => renamed.bindings.get(index).map_or(name, String::as_str),
+            None => name,
+        };
+
+        self.bindings.push(Binding {
+            name: name.to_string(),
+            kind,
+            is_renamable: is_renamable && !kind.is_reserved(name),
+            positions: position.into_iter().collect(),
+        });
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(index);
+        }
+    }
+
+    /// Declares the binding the passed identifier names.
+    fn declare(&mut self, token: &Token, kind: BindingKind, is_renamable: bool) {
+        if let TokenType::Identifier(name) = &token.token_type {
+            self.push_binding(name, kind, is_renamable, Some(token.start));
+        }
+    }
+
+    /// Resolves the passed identifier to the innermost binding it can refer to.
+    fn reference(&mut self, token: &Token, kind: BindingKind) {
+        let TokenType::Identifier(name) = &token.token_type else {
+            return;
+        };
+        let index = self.references.len();
+        let name = match self.renamed {
+            Some(renamed) => renamed.references.get(index).map_or(name.as_str(), String::as_str),
+            None => name.as_str(),
+        };
+
+        let binding = self.scopes.iter().rev().find_map(|scope| {
+            scope.iter().rev().copied().find(|binding| {
+                self.bindings[*binding].kind == kind && self.bindings[*binding].name == name
+            })
+        });
+        if let Some(binding) = binding {
+            if self.is_in_interpolated_string {
+                self.bindings[binding].is_renamable = false;
+            } else {
+                self.bindings[binding].positions.push(token.start);
+            }
+        }
+
+        self.references.push(Reference {
+            name: name.to_string(),
+            binding,
+        });
+    }
+
+    /// Walks the passed block in a new scope, where the types it defines are
+    /// declared first, as they can be used before their definition.
+    fn block(&mut self, block: &Block) {
+        self.scopes.push(Vec::new());
+        self.statements(block);
+        self.scopes.pop();
+    }
+
+    /// Walks the statements of the passed block in the current scope.
+    fn statements(&mut self, block: &Block) {
+        for (statement, _) in &block.statements {
+            match &**statement {
+                Statement::TypeDefinition(type_definition) => self.declare(
+                    &type_definition.type_name,
+                    BindingKind::Type,
+                    type_definition.export_keyword.is_none(),
+                ),
+                Statement::TypeFunction(type_function) => self.declare(
+                    &type_function.function_name,
+                    BindingKind::Type,
+                    type_function.export_keyword.is_none(),
+                ),
+                _ => (),
+            }
+        }
+
+        for (statement, _) in &block.statements {
+            self.statement(statement);
+        }
+
+        if let Some(TerminationStatement::Return {
+            expressions: Some(expressions),
+            ..
+        }) = block.last_statement.as_ref().map(|(statement, _)| &**statement)
+        {
+            for expression in expressions.iter() {
+                self.expression(expression);
+            }
+        }
+    }
+
+    /// Walks the passed statement.
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::LocalFunction(local_function) => {
+                self.declare(&local_function.function_name, BindingKind::Variable, true);
+                self.function(
+                    &local_function.generics,
+                    &local_function.parameters,
+                    &local_function.return_type,
+                    &local_function.body,
+                    false,
+                );
+            }
+            Statement::LocalAssignment(local_assignment) => {
+                for expression in local_assignment.expressions.iter() {
+                    self.expression(expression);
+                }
+                for name in local_assignment.name_list.iter() {
+                    self.optional_type(&name.r#type);
+                }
+                for name in local_assignment.name_list.iter() {
+                    self.declare(&name.name, BindingKind::Variable, true);
+                }
+            }
+            Statement::TypeDefinition(type_definition) => {
+                self.scopes.push(Vec::new());
+                self.generics(&type_definition.generics);
+                self.type_value(&type_definition.type_value);
+                self.scopes.pop();
+            }
+            Statement::IfStatement(if_statement) => {
+                self.expression(&if_statement.condition);
+                self.block(&if_statement.body);
+
+                for else_if_statement in &if_statement.else_if_statements {
+                    self.expression(&else_if_statement.condition);
+                    self.block(&else_if_statement.body);
+                }
+                if let Some(else_statement) = &if_statement.else_statement {
+                    self.block(&else_statement.body);
+                }
+            }
+            Statement::DoBlock(do_block) => self.block(&do_block.body),
+            Statement::GenericFor(generic_for) => {
+                for expression in generic_for.expressions.iter() {
+                    self.expression(expression);
+                }
+
+                self.scopes.push(Vec::new());
+                for name in generic_for.names.iter() {
+                    self.optional_type(&name.r#type);
+                    self.declare(&name.name, BindingKind::Variable, true);
+                }
+                self.block(&generic_for.do_block.body);
+                self.scopes.pop();
+            }
+            Statement::NumericalFor(numerical_for) => {
+                self.expression(&numerical_for.start);
+                self.expression(&numerical_for.end);
+                if let Some(step) = &numerical_for.step {
+                    self.expression(step);
+                }
+
+                self.scopes.push(Vec::new());
+                self.optional_type(&numerical_for.variable.r#type);
+                self.declare(&numerical_for.variable.name, BindingKind::Variable, true);
+                self.block(&numerical_for.do_block.body);
+                self.scopes.pop();
+            }
+            // The condition can use the locals of the body.
+            Statement::RepeatBlock(repeat_block) => {
+                self.scopes.push(Vec::new());
+                self.statements(&repeat_block.body);
+                self.expression(&repeat_block.condition);
+                self.scopes.pop();
+            }
+            Statement::WhileLoop(while_loop) => {
+                self.expression(&while_loop.condition);
+                self.block(&while_loop.do_block.body);
+            }
+            Statement::SetExpression(set_expression) => {
+                for value in set_expression.values.iter() {
+                    self.expression(value);
+                }
+                for variable in set_expression.variables.iter() {
+                    self.var(variable);
+                }
+            }
+            Statement::CompoundSetExpression(compound_set_expression) => {
+                self.expression(&compound_set_expression.value);
+                self.var(&compound_set_expression.variable);
+            }
+            Statement::FunctionCall(function_call) => self.function_call(function_call),
+            Statement::GlobalFunction(global_function) => {
+                let is_method = match &global_function.function_name {
+                    GlobalFunctionName::SimpleName(name) => {
+                        self.reference(name, BindingKind::Variable);
+                        false
+                    }
+                    GlobalFunctionName::Table {
+                        table,
+                        keys,
+                        method,
+                    } => {
+                        self.reference(table, BindingKind::Variable);
+                        self.table_access_keys(keys);
+                        method.is_some()
+                    }
+                };
+
+                self.function(
+                    &global_function.generics,
+                    &global_function.parameters,
+                    &global_function.return_type,
+                    &global_function.body,
+                    is_method,
+                );
+            }
+            Statement::TypeFunction(type_function) => self.function(
+                &type_function.generics,
+                &type_function.parameters,
+                &type_function.return_type,
+                &type_function.body,
+                false,
+            ),
+            Statement::EndOfFile(_) | Statement::ERROR => (),
+        }
+    }
+
+    /// Walks a function, whose generics and parameters, including `self` for
+    /// methods, are declared in the scope of its body.
+    fn function(
+        &mut self,
+        generics: &Option<Pointer<GenericDeclaration>>,
+        parameters: &BracketedList<Parameter>,
+        return_type: &Option<Pointer<TypeValue>>,
+        body: &Block,
+        is_method: bool,
+    ) {
+        self.scopes.push(Vec::new());
+        self.generics(generics);
+
+        if is_method {
+            self.push_binding("self", BindingKind::Variable, false, None);
+        }
+        for parameter in parameters.iter() {
+            self.optional_type(&parameter.r#type);
+        }
+        for parameter in parameters.iter() {
+            self.declare(&parameter.name, BindingKind::Variable, true);
+        }
+
+        self.optional_type(return_type);
+        self.block(body);
+        self.scopes.pop();
+    }
+
+    /// Declares the passed generic type parameters in the current scope.
+    fn generics(&mut self, generics: &Option<Pointer<GenericDeclaration>>) {
+        let Some(generics) = generics else {
+            return;
+        };
+
+        for generic in generics.iter() {
+            if let GenericParameterInfo::Name(name) | GenericParameterInfo::Pack { name, .. } =
+                &generic.parameter
+            {
+                self.declare(name, BindingKind::Type, true);
+            }
+        }
+        for generic in generics.iter() {
+            match &generic.default {
+                Some(GenericParameterInfoDefault::Name(name)) => {
+                    self.reference(name, BindingKind::Type)
+                }
+                Some(GenericParameterInfoDefault::Pack(type_value)) => {
+                    self.type_value(type_value)
+                }
+                Some(GenericParameterInfoDefault::ERROR) | None => (),
+            }
+        }
+    }
+
+    /// Walks the passed expression.
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::String(token) => self.string(token),
+            Expression::Closure(closure) => self.function(
+                &closure.generics,
+                &closure.parameters,
+                &closure.return_type,
+                &closure.body,
+                false,
+            ),
+            Expression::FunctionCall(function_call) => self.function_call(function_call),
+            Expression::ExpressionWrap(expression_wrap) => self.expression(&expression_wrap.item),
+            Expression::Var(var) => self.var(var),
+            Expression::Table(table) => self.table(table),
+            Expression::UnaryExpression { expression, .. } => self.expression(expression),
+            Expression::BinaryExpression { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expression::TypeCast {
+                expression,
+                cast_to,
+                ..
+            } => {
+                self.expression(expression);
+                self.type_value(cast_to);
+            }
+            Expression::IfExpression(if_expression) => {
+                self.expression(&if_expression.condition);
+                self.expression(&if_expression.if_expression);
+                for else_if_expression in if_expression.else_if_expressions.iter() {
+                    self.expression(&else_if_expression.condition);
+                    self.expression(&else_if_expression.expression);
+                }
+                self.expression(&if_expression.else_expression);
+            }
+            Expression::ERROR
+            | Expression::Nil(_)
+            | Expression::Boolean(_)
+            | Expression::Number(_) => (),
+        }
+    }
+
+    /// Walks the expressions of the passed string, if it's interpolated.
+    fn string(&mut self, token: &Token) {
+        let Some(string) = token.token_type.try_as_string() else {
+            return;
+        };
+        if !string.starts_with('`') {
+            return;
+        }
+
+        for segment in split_interpolated_string(&string) {
+            let Segment::Expression(code) = segment else {
+                continue;
+            };
+            let Some(cst) = parse_expression(code) else {
+                continue;
+            };
+
+            if let Some(expression) = expression_of(&cst) {
+                let is_in_interpolated_string = self.is_in_interpolated_string;
+                self.is_in_interpolated_string = true;
+                self.expression(expression);
+                self.is_in_interpolated_string = is_in_interpolated_string;
+            }
+        }
+    }
+
+    /// Walks the passed variable, which is only a reference if it's a name, as
+    /// table fields aren't bindings.
+    fn var(&mut self, var: &Var) {
+        match var {
+            Var::Name(name) => self.reference(name, BindingKind::Variable),
+            Var::TableAccess(table_access) => {
+                match &table_access.prefix {
+                    TableAccessPrefix::Name(name) => self.reference(name, BindingKind::Variable),
+                    TableAccessPrefix::FunctionCall(function_call) => {
+                        self.function_call(function_call)
+                    }
+                    TableAccessPrefix::ExpressionWrap(expression_wrap) => {
+                        self.expression(&expression_wrap.item)
+                    }
+                }
+                self.table_access_keys(&table_access.accessed_keys);
+            }
+            Var::ERROR => (),
+        }
+    }
+
+    /// Walks the expressions of the passed keys, like `[key]`.
+    fn table_access_keys(&mut self, keys: &[TableAccessKey]) {
+        for key in keys {
+            if let TableAccessKey::Expression(key) = key {
+                self.table_key(key);
+            }
+        }
+    }
+
+    /// Walks the passed prefix expression.
+    fn prefix_exp(&mut self, prefix_exp: &PrefixExp) {
+        match prefix_exp {
+            PrefixExp::Var(var) => self.var(var),
+            PrefixExp::FunctionCall(function_call) => self.function_call(function_call),
+            PrefixExp::ExpressionWrap(expression_wrap) => self.expression(&expression_wrap.item),
+        }
+    }
+
+    /// Walks the passed function call. Methods are table fields, so they
+    /// aren't references.
+    fn function_call(&mut self, function_call: &FunctionCall) {
+        match &function_call.invoked {
+            FunctionCallInvoked::Function(function) => self.prefix_exp(function),
+            FunctionCallInvoked::TableMethod { table, .. } => self.prefix_exp(table),
+        }
+
+        match &function_call.arguments {
+            FunctionArguments::String(string) => self.string(string),
+            FunctionArguments::Table(table) => self.table(table),
+            FunctionArguments::List(arguments) => {
+                for argument in arguments.iter() {
+                    if let FunctionArgument::Expression(expression) = &***argument {
+                        self.expression(expression);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Walks the passed table key. Keys that are names are table fields, so
+    /// they aren't references.
+    fn table_key(&mut self, table_key: &TableKey) {
+        match table_key {
+            TableKey::Expression(expression) => self.expression(&expression.item),
+            TableKey::Type(type_value) => self.type_value(&type_value.item),
+            TableKey::ERROR
+            | TableKey::UndefinedNumber(_)
+            | TableKey::UndefinedString(_)
+            | TableKey::Simple(_) => (),
+        }
+    }
+
+    /// Walks the passed table, which is either a value or a type.
+    fn table(&mut self, table: &Table) {
+        for field in table.0.iter() {
+            self.table_key(&field.key);
+
+            match &*field.value {
+                TableFieldValue::Expression(expression) => self.expression(expression),
+                TableFieldValue::Type(type_value) => self.type_value(type_value),
+                TableFieldValue::ERROR | TableFieldValue::VariadicValues(_) => (),
+            }
+        }
+    }
+
+    /// Walks the passed type, if any.
+    fn optional_type(&mut self, type_value: &Option<Pointer<TypeValue>>) {
+        if let Some(type_value) = type_value {
+            self.type_value(type_value);
+        }
+    }
+
+    /// Walks the passed generic arguments of a type, if any.
+    fn type_arguments(&mut self, generics: &Option<Pointer<BracketedList<Pointer<TypeValue>>>>) {
+        if let Some(generics) = generics {
+            for generic in generics.iter() {
+                self.type_value(generic);
+            }
+        }
+    }
+
+    /// Walks the passed type.
+    fn type_value(&mut self, type_value: &TypeValue) {
+        match type_value {
+            TypeValue::Wrap(type_value) => self.type_value(&type_value.item),
+            TypeValue::Function {
+                generics,
+                parameters,
+                return_type,
+                ..
+            } => {
+                self.scopes.push(Vec::new());
+                self.generics(generics);
+                for parameter in parameters.iter() {
+                    match &**parameter {
+                        ParameterTypeName::Normal(name) => self.optional_type(&name.r#type),
+                        ParameterTypeName::Type(type_value) => self.type_value(type_value),
+                    }
+                }
+                self.type_value(return_type);
+                self.scopes.pop();
+            }
+            TypeValue::Basic { base, generics } => {
+                self.reference(base, BindingKind::Type);
+                self.type_arguments(generics);
+            }
+            TypeValue::GenericPack { name, .. } | TypeValue::VariadicPack { name, .. } => {
+                self.reference(name, BindingKind::Type)
+            }
+            // The module is a value, and the type one of its exported types.
+            TypeValue::Module {
+                module, generics, ..
+            } => {
+                self.reference(module, BindingKind::Variable);
+                self.type_arguments(generics);
+            }
+            TypeValue::Intersection { left, right, .. } | TypeValue::Union { left, right, .. } => {
+                self.type_value(left);
+                self.type_value(right);
+            }
+            TypeValue::Optional { base, .. } => self.type_value(base),
+            TypeValue::Table(table) => self.table(table),
+            TypeValue::Typeof { inner, .. } => self.expression(&inner.item),
+            TypeValue::Tuple(type_values) => {
+                for type_value in type_values.iter() {
+                    self.type_value(type_value);
+                }
+            }
+            TypeValue::Variadic { type_value, .. } => self.type_value(type_value),
+            TypeValue::ERROR
+            | TypeValue::String(_)
+            | TypeValue::Boolean(_)
+            | TypeValue::Nil(_) => (),
+        }
+    }
+}
+
+/// Resolves the identifiers of the passed block, with the names of `renamed`
+/// instead of their own if it's set.
+fn resolve<'a>(block: &Block, renamed: Option<Renamed<'a>>) -> Resolver<'a> {
+    let mut resolver = Resolver {
+        renamed,
+        ..Default::default()
+    };
+    resolver.block(block);
+
+    resolver
+}
+
+/// Whether or not the passed name is a valid identifier.
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|character| character.is_ascii_alphabetic() || character == '_')
+        && name
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || character == '_')
+}
+
+/// Gets the new name of each binding of the passed block, which is its own
+/// name if it can't be renamed, or is in the allow list of its naming
+/// convention. A binding isn't renamed if, once renamed, an identifier would
+/// refer to another binding than it did, like when two bindings get the same
+/// name, or a binding gets the name of a global used in its scope.
+fn binding_names(block: &Block, config: &Config) -> (Resolver<'static>, Vec<String>) {
+    let original = resolve(block, None);
+    let mut names = original
+        .bindings
+        .iter()
+        .map(|binding| {
+            let name = binding.kind.naming_convention(config).apply(&binding.name);
+
+            if binding.is_renamable
+                && !binding.kind.allow_list(config).contains(&binding.name)
+                && is_identifier(&name)
+                && !binding.kind.is_reserved(&name)
+            {
+                name
+            } else {
+                binding.name.clone()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    loop {
+        let reference_names = original
+            .references
+            .iter()
+            .map(|reference| match reference.binding {
+                Some(binding) => names[binding].clone(),
+                None => reference.name.clone(),
+            })
+            .collect::<Vec<_>>();
+        let renamed = resolve(
+            block,
+            Some(Renamed {
+                bindings: &names,
+                references: &reference_names,
+            }),
+        );
+
+        let mut is_done = true;
+        for (reference, renamed_reference) in original.references.iter().zip(&renamed.references) {
+            if reference.binding == renamed_reference.binding {
+                continue;
+            }
+
+            for binding in [reference.binding, renamed_reference.binding]
+                .into_iter()
+                .flatten()
+            {
+                if names[binding] != original.bindings[binding].name {
+                    names[binding].clone_from(&original.bindings[binding].name);
+                    is_done = false;
+                }
+            }
+        }
+
+        if is_done {
+            return (original, names);
+        }
+    }
+}
+
+/// Renames the identifiers of the passed serialized node that start at one of
+/// the passed positions.
+fn rename_identifiers(value: &mut Value, renames: &HashMap<Position, String>) {
+    match value {
+        Value::Object(map) if map.contains_key("token_type") => {
+            let position = map
+                .get("start")
+                .and_then(|start| serde_json::from_value::<Position>(start.clone()).ok());
+
+            if let Some(name) = position.and_then(|position| renames.get(&position))
+                && let Some(identifier) = map
+                    .get_mut("token_type")
+                    .and_then(|token_type| token_type.get_mut("Identifier"))
+            {
+                *identifier = Value::String(name.to_string());
+            }
+        }
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|value| rename_identifiers(value, renames)),
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| rename_identifiers(value, renames)),
+        _ => (),
+    }
+}
+
+/// Finds the new name of the identifiers of the local bindings of the passed
+/// block, declared in it, with the naming conventions of the passed config.
+/// Returns the new names by the start of the identifiers that get them, in
+/// declarations and references.
+pub(crate) fn find_renames(block: &Block, config: &Config) -> HashMap<Position, String> {
+    if config.variable_casing == NamingConvention::None
+        && config.type_casing == NamingConvention::None
+    {
+        return HashMap::new();
+    }
+
+    let (resolver, names) = binding_names(block, config);
+
+    resolver
+        .bindings
+        .into_iter()
+        .zip(names)
+        .filter(|(binding, name)| binding.name != *name)
+        .flat_map(|(binding, name)| {
+            binding
+                .positions
+                .into_iter()
+                .map(move |position| (position, name.clone()))
+        })
+        .collect()
+}
+
+/// Renames the identifiers of the passed block with the new names found by
+/// [`find_renames`]. The block is borrowed as it is if nothing is renamed. The
+/// identifiers are renamed in the serialized block, which fails if it can't be
+/// deserialized back.
+pub(crate) fn rename_bindings<'a>(
+    block: &'a Block,
+    renames: &HashMap<Position, String>,
+) -> Result<Cow<'a, Block>, JsonError> {
+    if renames.is_empty() {
+        return Ok(Cow::Borrowed(block));
+    }
+
+    let mut value = serialize(block);
+    rename_identifiers(&mut value, renames);
+
+    serde_json::from_value(value).map(Cow::Owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use luau_parser::parser::Parser;
+
+    use crate::{Config, NamingConvention, format_with_config};
+
+    /// Formats the passed code with the passed config.
+    fn format_code(code: &str, config: &Config) -> String {
+        format_with_config(&Parser::new(code).parse(""), config).unwrap()
+    }
+
+    /// Checks that only local bindings are renamed, and not globals or fields.
+    #[test]
+    fn only_locals_are_renamed() {
+        let code = "local fooBar = 1\nprint(fooBar, globalThing)\nlocal t = {}\n\
+                    t.someField = fooBar\nt:someMethod()\nlocal u = { someKey = fooBar }\n\
+                    function globalFunc(someArg)\n    return someArg\nend\nsomeGlobal = 1\n";
+        let config = Config {
+            variable_casing: NamingConvention::Snake,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format_code(code, &config),
+            "local foo_bar = 1\nprint(foo_bar, globalThing)\nlocal t = {}\n\
+             t.someField = foo_bar\nt:someMethod()\nlocal u = { someKey = foo_bar }\n\
+             function globalFunc(some_arg)\n    return some_arg\nend\nsomeGlobal = 1\n"
+        );
+    }
+
+    /// Checks that names on the allow lists aren't renamed.
+    #[test]
+    fn allowed_names_are_not_renamed() {
+        let code = "local fooBar = 1\nlocal HTTPService = 2\ntype someType = number\n\
+                    type otherType = someType\n";
+        let config = Config {
+            variable_casing: NamingConvention::Snake,
+            variable_casing_allow_list: vec!["HTTPService".to_string()],
+            type_casing: NamingConvention::Pascal,
+            type_casing_allow_list: vec!["someType".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format_code(code, &config),
+            "local foo_bar = 1\nlocal HTTPService = 2\ntype someType = number\n\
+             type OtherType = someType\n"
+        );
+    }
+}
@@ -0,0 +1,432 @@
+//! Formatting [`Cst`]s with syntax errors, by only formatting the statements
+//! around the errors, at any depth, and keeping everything else as-is.
+
+use luau_parser::{
+    parser::Parser,
+    prelude::{Block, Error, Position},
+};
+use std::{collections::HashMap, ops::Range};
+
+use crate::{
+    check_formatted_code, comments,
+    config::Config,
+    formatter::format_block,
+    items::{Item, LocatedItem, end_of_trailing_comments, locate_items},
+    syntax_errors,
+    traits::Indentation,
+};
+
+/// The lines of a source code.
+struct Lines<'a> {
+    /// The source code.
+    source: &'a str,
+
+    /// The byte offset of the start of each line.
+    starts: Vec<usize>,
+}
+
+impl<'a> Lines<'a> {
+    /// Create a new [`Lines`] for the passed source code.
+    fn new(source: &'a str) -> Self {
+        let starts = [0]
+            .into_iter()
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        Self { source, starts }
+    }
+
+    /// The line the passed byte offset is at.
+    #[inline]
+    fn line_of(&self, offset: usize) -> usize {
+        self.starts.partition_point(|start| *start <= offset) - 1
+    }
+
+    /// The byte offset of the passed position, whose character is counted in
+    /// `char`s like the lexer does.
+    fn offset_of(&self, position: Position) -> usize {
+        let Some(start) = self.starts.get(position.line as usize) else {
+            return self.source.len();
+        };
+
+        self.source[*start..]
+            .char_indices()
+            .nth(position.character as usize)
+            .map_or(self.source.len(), |(i, _)| start + i)
+    }
+
+    /// The lines spanned by the passed byte range, ignoring whitespace around
+    /// it. Returns [`None`] if it's only whitespace.
+    fn lines_of(&self, range: Range<usize>) -> Option<Range<usize>> {
+        let text = &self.source[range.clone()];
+        let trimmed = text.trim();
+
+        if trimmed.is_empty() {
+            return None;
+        }
+
+        let start = range.start + (text.len() - text.trim_start().len());
+        let end = start + trimmed.len();
+
+        Some(self.line_of(start)..self.line_of(end - 1) + 1)
+    }
+}
+
+/// Marks the passed lines as erroneous.
+#[inline]
+fn mark_lines(erroneous_lines: &mut [bool], lines: Range<usize>) {
+    erroneous_lines[lines].fill(true);
+}
+
+/// Gets the lines of each of the passed items. The comments on the line of an
+/// item are formatted with it, so they aren't on the lines of the item after
+/// it, even though they're in its span.
+fn lines_of_items(located_items: &[LocatedItem], lines: &Lines) -> Vec<Option<Range<usize>>> {
+    let mut item_lines = Vec::with_capacity(located_items.len());
+    let mut previous_item: Option<&LocatedItem> = None;
+
+    for located_item in located_items {
+        let span = located_item.span.clone().map(|mut span| {
+            if let Some(previous_item) = previous_item
+                && previous_item
+                    .span
+                    .as_ref()
+                    .is_some_and(|previous_span| previous_span.end == span.start)
+            {
+                span.start = end_of_trailing_comments(&previous_item.item, lines.source, span.start)
+                    .min(span.end);
+            }
+
+            span
+        });
+
+        item_lines.push(span.and_then(|span| lines.lines_of(span)));
+        previous_item = Some(located_item);
+    }
+
+    item_lines
+}
+
+/// Finds the items that share a line with an error, a gap, or another
+/// erroneous item. Items that weren't found in the source code are erroneous
+/// too. Returns whether each item is erroneous.
+fn find_erroneous_items(
+    located_items: &[LocatedItem],
+    gaps: &[Range<usize>],
+    errors: &[&Error],
+    lines: &Lines,
+) -> Vec<bool> {
+    let mut is_erroneous: Vec<bool> = located_items
+        .iter()
+        .map(|located_item| located_item.span.is_none())
+        .collect();
+    let item_lines = lines_of_items(located_items, lines);
+    let mut erroneous_lines = vec![false; lines.starts.len()];
+
+    for gap in gaps {
+        if let Some(range) = lines.lines_of(gap.clone()) {
+            mark_lines(&mut erroneous_lines, range);
+        }
+    }
+    for error in errors {
+        let line = lines.line_of(lines.offset_of(error.start()));
+        mark_lines(&mut erroneous_lines, line..line + 1);
+    }
+
+    loop {
+        let mut changed = false;
+
+        for (range, is_erroneous) in item_lines.iter().zip(is_erroneous.iter_mut()) {
+            let Some(range) = range.clone() else {
+                continue;
+            };
+
+            if !*is_erroneous && range.clone().any(|line| erroneous_lines[line]) {
+                *is_erroneous = true;
+            }
+            if *is_erroneous && !range.clone().all(|line| erroneous_lines[line]) {
+                mark_lines(&mut erroneous_lines, range);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    is_erroneous
+}
+
+/// Groups consecutive items that aren't erroneous. Returns the byte range of
+/// each group in the source code, including the trailing comments of its last
+/// item, as they're formatted with it.
+fn group_items(
+    located_items: &[LocatedItem],
+    is_erroneous: &[bool],
+    source: &str,
+) -> Vec<Range<usize>> {
+    let mut groups: Vec<(Range<usize>, Item)> = Vec::new();
+    let mut is_continuing = false;
+
+    for (located_item, is_erroneous) in located_items.iter().zip(is_erroneous) {
+        match &located_item.span {
+            Some(span) if !is_erroneous => {
+                match groups.last_mut() {
+                    Some((range, item)) if is_continuing && range.end == span.start => {
+                        range.end = span.end;
+                        *item = located_item.item;
+                    }
+                    _ => groups.push((span.clone(), located_item.item)),
+                }
+
+                is_continuing = true;
+            }
+            _ => is_continuing = false,
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(range, last_item)| {
+            range.start..end_of_trailing_comments(&last_item, source, range.end)
+        })
+        // Empty items, like the end of the file, aren't worth formatting on
+        // their own.
+        .filter(|range| !source[range.clone()].trim().is_empty())
+        .collect()
+}
+
+/// Parses the passed group of statements again on its own and formats it at
+/// the passed indentation. Returns [`None`] if it still has errors or if
+/// formatting it fails.
+fn format_group(code: &str, indentation: Indentation, config: &Config) -> Option<String> {
+    let cst = Parser::new(code).parse("");
+    if !syntax_errors(&cst).is_empty() {
+        return None;
+    }
+
+    let formatted_code = format_block(&cst.block, indentation, config);
+
+    check_formatted_code(&cst, formatted_code, &HashMap::new(), config).ok()
+}
+
+/// Finds the statements of the passed nested [`Block`], searching from
+/// `start`, which is before the code of the statement it's in. Returns where
+/// its first statement starts, to pass it to [`locate_items`] again, as the
+/// code before it would be a gap otherwise.
+fn nested_block_start(block: &Block, source: &str, start: usize) -> Option<usize> {
+    let (located_items, _) = locate_items(block, source, start);
+
+    located_items
+        .iter()
+        .find_map(|located_item| located_item.span.as_ref())
+        .map(|span| span.start)
+}
+
+/// Formats the statements of the passed [`Block`], which starts being searched
+/// for at `start` in the source code of `lines`, that are far enough from
+/// syntax errors, and recurses into the bodies of the ones that aren't. Pushes
+/// the byte range of each formatted group of statements and the code to
+/// replace it with to `edits`. Returns the end of the last statement that was
+/// found in the source code.
+fn format_block_tolerant(
+    block: &Block,
+    start: usize,
+    indentation: Indentation,
+    config: &Config,
+    errors: &[&Error],
+    lines: &Lines,
+    edits: &mut Vec<(Range<usize>, String)>,
+) -> usize {
+    let source = lines.source;
+    let (located_items, mut gaps) = locate_items(block, source, start);
+
+    // Code after the last statement of a nested block, like `end`, belongs to
+    // the statement around it.
+    if indentation == 0 {
+        let end = located_items
+            .iter()
+            .rev()
+            .find_map(|located_item| located_item.span.as_ref())
+            .map_or(0, |span| span.end);
+        let final_trivia = located_items
+            .last()
+            .map(|located_item| located_item.item.print_final_trivia())
+            .unwrap_or_default();
+        if source[end..] != final_trivia {
+            gaps.push(end..source.len());
+        }
+    }
+
+    let is_erroneous = find_erroneous_items(&located_items, &gaps, errors, lines);
+    let groups = group_items(&located_items, &is_erroneous, source);
+
+    let mut cursor = start;
+    let mut groups = groups.into_iter().peekable();
+
+    for (located_item, is_erroneous) in located_items.iter().zip(is_erroneous) {
+        let item_start = located_item.span.as_ref().map_or(cursor, |span| span.start);
+
+        while let Some(range) = groups.next_if(|range| range.start <= item_start) {
+            let Some(formatted_group) = format_group(&source[range.clone()], indentation, config)
+            else {
+                continue;
+            };
+
+            // Only the lines before the group are kept, its indentation is
+            // added back.
+            let leading_spaces = &source[range.start..];
+            let leading_spaces =
+                &leading_spaces[..leading_spaces.len() - leading_spaces.trim_start().len()];
+            let new_lines = leading_spaces.matches('\n').count().max(1);
+            let new_lines = if config.keep_statements_spacing {
+                new_lines
+            } else {
+                new_lines.min(2)
+            };
+
+            let mut replacement = if range.start == 0 {
+                String::new()
+            } else {
+                config.newline_style.to_string().repeat(new_lines)
+                    + &config.indent_style.to_string(indentation, config)
+            };
+
+            if source[range.end..].trim().is_empty() {
+                replacement.push_str(formatted_group.trim_start());
+                edits.push((range.start..source.len(), replacement));
+            } else {
+                replacement.push_str(formatted_group.trim());
+                edits.push((range, replacement));
+            }
+        }
+
+        if is_erroneous {
+            let mut nested_cursor = item_start;
+
+            for nested_block in located_item.item.nested_blocks() {
+                let Some(nested_start) = nested_block_start(nested_block, source, nested_cursor)
+                else {
+                    continue;
+                };
+
+                nested_cursor = format_block_tolerant(
+                    nested_block,
+                    nested_start,
+                    indentation + 1,
+                    config,
+                    errors,
+                    lines,
+                    edits,
+                );
+            }
+        }
+
+        if let Some(span) = &located_item.span {
+            cursor = span.end;
+        }
+    }
+
+    cursor
+}
+
+/// Returns the passed formatted code, or `source` as-is if a comment of it
+/// isn't in the formatted code.
+fn keep_if_comment_lost(source: &str, formatted_code: String) -> String {
+    if comments::find_lost_comment(source, &formatted_code).is_some() {
+        source.to_string()
+    } else {
+        formatted_code
+    }
+}
+
+/// Formats the statements of `block` that are far enough from syntax errors,
+/// including the ones in the bodies of statements that aren't, and keeps the
+/// rest exactly as it is in `source`. Each group of statements is parsed again
+/// on its own, so parts the parser couldn't parse never reach the formatter;
+/// groups that still have errors are kept as-is. `source` is returned as-is if
+/// a comment would be lost.
+pub(crate) fn format_tolerant(
+    block: &Block,
+    source: &str,
+    config: &Config,
+    errors: &[&Error],
+) -> String {
+    let lines = Lines::new(source);
+    let mut edits = Vec::new();
+
+    format_block_tolerant(block, 0, 0, config, errors, &lines, &mut edits);
+
+    let mut formatted_code = String::new();
+    let mut cursor = 0;
+
+    for (range, replacement) in edits {
+        formatted_code.push_str(&source[cursor..range.start]);
+        formatted_code.push_str(&replacement);
+        cursor = range.end;
+    }
+
+    formatted_code.push_str(&source[cursor..]);
+
+    keep_if_comment_lost(source, formatted_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use luau_parser::parser::Parser;
+
+    use super::keep_if_comment_lost;
+    use crate::{Config, format_tolerant};
+
+    /// Formats the passed code, which has syntax errors, with the default config.
+    fn format_code(code: &str) -> String {
+        format_tolerant(&Parser::new(code).parse(""), code, &Config::default())
+    }
+
+    /// Checks that the statements in the body of a statement with a syntax error
+    /// are formatted.
+    #[test]
+    fn bodies_are_formatted() {
+        let code = "local   a=1\nfunction M.f()\n    print(1 +)\n\n    if  a then\n        \
+                    print( a )\n    end\nend\n";
+
+        assert_eq!(
+            format_code(code),
+            "local a = 1\nfunction M.f()\n    print(1 +)\n\n    if a then\n        print(a)\n    \
+             end\nend\n"
+        );
+    }
+
+    /// Checks that statements on the line of a statement with a syntax error are
+    /// kept as-is, and that the ones on the next lines are formatted.
+    #[test]
+    fn statements_on_erroneous_lines_are_kept() {
+        let code = "local   a=1; print(1 +)\nlocal  b=2\n";
+
+        assert_eq!(format_code(code), "local   a=1; print(1 +)\nlocal b = 2\n");
+    }
+
+    /// Checks that the comments after a statement don't put it on the lines of
+    /// the statement with a syntax error after them.
+    #[test]
+    fn trailing_comments_are_formatted_with_their_statement() {
+        let code = "local  a=1 -- a\n-- b\nprint(1 +)\n";
+
+        assert_eq!(format_code(code), "local a = 1 -- a\n-- b\nprint(1 +)\n");
+    }
+
+    #[test]
+    fn source_is_kept_if_a_comment_is_lost() {
+        let source = "local  a=1 -- c\nprint(1 +)\n";
+
+        assert_eq!(
+            keep_if_comment_lost(source, "local a = 1\nprint(1 +)\n".to_string()),
+            source
+        );
+        assert_eq!(
+            keep_if_comment_lost(source, "local a = 1 -- c\nprint(1 +)\n".to_string()),
+            "local a = 1 -- c\nprint(1 +)\n"
+        );
+    }
+}
@@ -0,0 +1,761 @@
+//! Checking that formatted code means the same as the code it was formatted
+//! from, by parsing it again and comparing both [`Cst`]s.
+
+use luau_parser::{
+    parser::Parser,
+    prelude::{Position, Statement, TokenType},
+    types::{Cst, Pointer},
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter, Result as FmtResult},
+    iter::Peekable,
+    str::Chars,
+};
+
+use crate::{
+    config::{Config, StringWrap},
+    formatter::{BlockType, get_block_type},
+    interpolation::{Segment, expression_of, parse_expression, split_interpolated_string},
+    resolver::find_renames,
+    syntax_errors,
+};
+
+/// Keys of serialized nodes that don't affect the meaning of the code.
+const IGNORED_KEYS: [&str; 4] = ["start", "end", "leading_trivia", "trailing_trivia"];
+
+/// The first node where formatted code stops meaning the same as the code it
+/// was formatted from.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Divergence {
+    /// The path to the node from the main block, like
+    /// `statements[2].LocalAssignment.expressions[0]`.
+    pub node: String,
+
+    /// Where the node starts in the original code, if it's there.
+    pub original: Option<Position>,
+
+    /// Where the node starts in the formatted code, if it's there.
+    pub formatted: Option<Position>,
+
+    /// What differs.
+    pub message: String,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        if self.node.is_empty() {
+            write!(f, "{}", self.message)?;
+        } else {
+            write!(f, "`{}` differs: {}", self.node, self.message)?;
+        }
+
+        match (self.original, self.formatted) {
+            (Some(original), Some(formatted)) => write!(
+                f,
+                " (at {}:{} in the original code, {}:{} in the formatted code)",
+                original.line + 1,
+                original.character + 1,
+                formatted.line + 1,
+                formatted.character + 1,
+            ),
+            (Some(position), None) => write!(
+                f,
+                " (at {}:{} in the original code)",
+                position.line + 1,
+                position.character + 1,
+            ),
+            (None, Some(position)) => write!(
+                f,
+                " (at {}:{} in the formatted code)",
+                position.line + 1,
+                position.character + 1,
+            ),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Serializes the passed node, to compare it or search it without going through
+/// each of its types.
+pub(crate) fn serialize<T: Serialize>(node: &T) -> Value {
+    // Serializing can't fail as all keys are strings.
+    serde_json::to_value(node).unwrap_or_default()
+}
+
+/// Gets the start of the first token in the passed serialized node. Serialized
+/// nodes keep the order of their fields, which is the order of the code.
+pub(crate) fn start_of(value: &Value) -> Option<Position> {
+    match value {
+        Value::Object(map) => match map.get("start") {
+            Some(start) if map.contains_key("token_type") => {
+                serde_json::from_value(start.clone()).ok()
+            }
+            _ => map.values().find_map(start_of),
+        },
+        Value::Array(values) => values.iter().find_map(start_of),
+        _ => None,
+    }
+}
+
+/// Gets the code of the passed serialized [`TokenType`].
+fn token_text(token_type: &Value) -> String {
+    serde_json::from_value::<TokenType>(token_type.clone())
+        .ok()
+        .and_then(|token_type| token_type.try_as_string())
+        .unwrap_or_else(|| token_type.to_string())
+}
+
+/// Gets the name of the variant of the passed serialized enum, if it's one.
+pub(crate) fn variant_of(value: &Value) -> Option<&str> {
+    match value {
+        Value::Object(map) if map.len() == 1 => map.keys().next().map(String::as_str),
+        _ => None,
+    }
+}
+
+/// Whether or not the passed serialized node is a `,` or a `;`, which can be
+/// added, removed, or swapped in lists and tables without changing anything.
+fn is_separator(value: &Value) -> bool {
+    matches!(
+        value.pointer("/token_type/Symbol").and_then(Value::as_str),
+        Some("Comma" | "Semicolon")
+    )
+}
+
+/// Gets the item of the passed serialized list item, dropping its separator.
+fn list_item(value: &Value) -> &Value {
+    match value {
+        Value::Object(map) if map.len() == 1 => match (map.get("Trailing"), map.get("NonTrailing"))
+        {
+            (Some(trailing), _) => trailing.get("item").unwrap_or(value),
+            (_, Some(non_trailing)) => non_trailing,
+            _ => value,
+        },
+        _ => value,
+    }
+}
+
+/// Turns `f("string")` and `f({ ... })` into `f "string"` and `f { ... }`,
+/// as parentheses around a single string or table argument don't change
+/// anything. Other single arguments are turned into their expression too, for
+/// strings split with `..`.
+fn normalize_arguments(value: &Value) -> Value {
+    let Some(items) = value.pointer("/List/item/items").and_then(Value::as_array) else {
+        return value.clone();
+    };
+
+    match items.as_slice() {
+        [item] => match list_item(item).get("Expression") {
+            Some(Value::Object(expression)) if expression.len() == 1 => {
+                match expression.iter().next() {
+                    Some((variant, argument)) => {
+                        Value::Object(Map::from_iter([(variant.clone(), argument.clone())]))
+                    }
+                    None => value.clone(),
+                }
+            }
+            _ => value.clone(),
+        },
+        _ => value.clone(),
+    }
+}
+
+/// Parses up to `max` digits of `chars`, in the passed radix, as the rest of a
+/// number that starts with `number`.
+fn parse_digits(chars: &mut Peekable<Chars>, radix: u32, max: usize, mut number: u32) -> u32 {
+    for _ in 0..max {
+        match chars.peek().and_then(|character| character.to_digit(radix)) {
+            Some(digit) => {
+                number = number * radix + digit;
+                chars.next();
+            }
+            None => break,
+        }
+    }
+
+    number
+}
+
+/// Gets the value of the passed string literal, with its quotes removed and its
+/// escape sequences resolved. Interpolated strings are kept as they are, and
+/// compared with [`Verifier::is_same_interpolated_string`].
+fn string_value(string: &str) -> Vec<u8> {
+    if let Some(rest) = string.strip_prefix('[') {
+        let level = rest.find('[').unwrap_or(0);
+        let content = &string[level + 2..string.len() - level - 2];
+        let content = content
+            .strip_prefix("\r\n")
+            .or_else(|| content.strip_prefix('\n'))
+            .unwrap_or(content);
+
+        return content.as_bytes().to_vec();
+    }
+    if string.starts_with('`') || string.len() < 2 {
+        return string.as_bytes().to_vec();
+    }
+
+    let mut value = Vec::new();
+    let mut chars = string[1..string.len() - 1].chars().peekable();
+
+    while let Some(character) = chars.next() {
+        if character != '\\' {
+            value.extend_from_slice(character.encode_utf8(&mut [0; 4]).as_bytes());
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => value.push(b'\n'),
+            Some('t') => value.push(b'\t'),
+            Some('r') => value.push(b'\r'),
+            Some('a') => value.push(7),
+            Some('b') => value.push(8),
+            Some('f') => value.push(12),
+            Some('v') => value.push(11),
+            Some('\r') => {
+                chars.next_if_eq(&'\n');
+                value.push(b'\n');
+            }
+            Some('z') => {
+                while chars
+                    .next_if(|character| character.is_ascii_whitespace())
+                    .is_some()
+                {}
+            }
+            Some('x') => value.push(parse_digits(&mut chars, 16, 2, 0) as u8),
+            Some('u') => {
+                chars.next_if_eq(&'{');
+                let code_point = parse_digits(&mut chars, 16, 8, 0);
+                chars.next_if_eq(&'}');
+
+                match char::from_u32(code_point) {
+                    Some(character) => {
+                        value.extend_from_slice(character.encode_utf8(&mut [0; 4]).as_bytes())
+                    }
+                    None => value.extend_from_slice(format!("\\u{{{code_point:x}}}").as_bytes()),
+                }
+            }
+            Some(digit) if digit.is_ascii_digit() => {
+                let first_digit = digit.to_digit(10).unwrap_or_default();
+                value.push(parse_digits(&mut chars, 10, 2, first_digit) as u8);
+            }
+            Some(character) => {
+                value.extend_from_slice(character.encode_utf8(&mut [0; 4]).as_bytes())
+            }
+            None => value.push(b'\\'),
+        }
+    }
+
+    value
+}
+
+/// Gets the value of the passed serialized expression if it's a string, or
+/// strings joined with `..`, maybe between parentheses, like the ones long
+/// strings are split into.
+fn concatenated_value(expression: &Value) -> Option<Vec<u8>> {
+    match variant_of(expression)? {
+        "String" => {
+            let string = expression.pointer("/String/token_type/Literal/String")?;
+
+            Some(string_value(string.as_object()?.values().next()?.as_str()?))
+        }
+        "BinaryExpression" => {
+            let binary_expression = &expression["BinaryExpression"];
+            if binary_expression.pointer("/operator/token_type/Operator")? != "Concatenation" {
+                return None;
+            }
+
+            let mut value = concatenated_value(&binary_expression["left"])?;
+            value.extend(concatenated_value(&binary_expression["right"])?);
+
+            Some(value)
+        }
+        "ExpressionWrap" => concatenated_value(expression.pointer("/ExpressionWrap/item")?),
+        _ => None,
+    }
+}
+
+/// Compares a [`Cst`] with the [`Cst`] of its formatted code.
+struct Verifier<'a> {
+    /// The config the code was formatted with.
+    config: &'a Config,
+
+    /// The new names of the renamed identifiers, by their start in the
+    /// original code.
+    renames: &'a HashMap<Position, String>,
+
+    /// The path to the compared node.
+    path: Vec<String>,
+}
+
+impl Verifier<'_> {
+    /// Creates the [`Divergence`] of the compared nodes.
+    fn diverge(&self, original: &Value, formatted: &Value, message: String) -> Divergence {
+        let mut node = String::new();
+
+        for segment in &self.path {
+            if !node.is_empty() && !segment.starts_with('[') {
+                node.push('.');
+            }
+            node.push_str(segment);
+        }
+
+        Divergence {
+            node,
+            original: start_of(original),
+            formatted: start_of(formatted),
+            message,
+        }
+    }
+
+    /// Compares `original` and `formatted` as the child `segment` of the
+    /// current node.
+    fn compare_child(
+        &mut self,
+        segment: String,
+        original: &Value,
+        formatted: &Value,
+    ) -> Result<(), Divergence> {
+        self.path.push(segment);
+        let result = self.compare(original, formatted);
+        self.path.pop();
+
+        result
+    }
+
+    /// Whether or not the passed names of the passed serialized identifier and
+    /// of the formatted one are the same, once the original one is renamed if
+    /// the formatter renamed it. Other renames aren't allowed.
+    fn is_same_identifier(
+        &self,
+        original: &Value,
+        original_name: &str,
+        formatted_name: &str,
+    ) -> bool {
+        let new_name = serde_json::from_value::<Position>(original["start"].clone())
+            .ok()
+            .and_then(|start| self.renames.get(&start));
+
+        new_name.map_or(original_name, String::as_str) == formatted_name
+    }
+
+    /// Whether or not `formatted` is the string `original` split into several
+    /// ones joined with `..`, which the config can ask for.
+    fn is_split_string(&self, original: &Value, formatted: &Value) -> bool {
+        self.config.string_wrap() == StringWrap::Concatenation
+            && variant_of(original) == Some("String")
+            && matches!(
+                variant_of(formatted),
+                Some("BinaryExpression" | "ExpressionWrap")
+            )
+            && concatenated_value(original)
+                .is_some_and(|value| concatenated_value(formatted) == Some(value))
+    }
+
+    /// Whether or not the passed interpolated strings have the same text, and
+    /// expressions meaning the same. The ones that can't be parsed must be the
+    /// same code, as they're kept as they are. Identifiers in them are never
+    /// renamed.
+    fn is_same_interpolated_string(&self, original: &str, formatted: &str) -> bool {
+        let no_renames = HashMap::new();
+        let mut verifier = Verifier {
+            config: self.config,
+            renames: &no_renames,
+            path: Vec::new(),
+        };
+
+        let original = split_interpolated_string(original);
+        let formatted = split_interpolated_string(formatted);
+
+        original.len() == formatted.len()
+            && original.into_iter().zip(formatted).all(|segments| match segments {
+                (Segment::Text(original), Segment::Text(formatted)) => original == formatted,
+                (Segment::Expression(original), Segment::Expression(formatted)) => {
+                    let original_cst = parse_expression(original);
+                    let formatted_cst = parse_expression(formatted);
+
+                    match (
+                        original_cst.as_deref().and_then(expression_of),
+                        formatted_cst.as_deref().and_then(expression_of),
+                    ) {
+                        (Some(original), Some(formatted)) => verifier
+                            .compare(&serialize(original), &serialize(formatted))
+                            .is_ok(),
+                        _ => original.trim() == formatted.trim(),
+                    }
+                }
+                _ => false,
+            })
+    }
+
+    /// Compares two serialized tokens, ignoring their trivia and position.
+    fn compare_tokens(&mut self, original: &Value, formatted: &Value) -> Result<(), Divergence> {
+        let original_type = &original["token_type"];
+        let formatted_type = &formatted["token_type"];
+
+        let is_same = match (
+            original_type.pointer("/Literal/String"),
+            formatted_type.pointer("/Literal/String"),
+        ) {
+            (Some(original_string), Some(formatted_string)) => {
+                let original_string = original_string
+                    .as_object()
+                    .and_then(|map| map.values().next())
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                let formatted_string = formatted_string
+                    .as_object()
+                    .and_then(|map| map.values().next())
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+
+                if original_string.starts_with('`') && formatted_string.starts_with('`') {
+                    self.is_same_interpolated_string(original_string, formatted_string)
+                } else {
+                    string_value(original_string) == string_value(formatted_string)
+                }
+            }
+            _ => match (
+                original_type.get("Identifier").and_then(Value::as_str),
+                formatted_type.get("Identifier").and_then(Value::as_str),
+            ) {
+                (Some(original_name), Some(formatted_name)) => {
+                    self.is_same_identifier(original, original_name, formatted_name)
+                }
+                _ => original_type == formatted_type,
+            },
+        };
+
+        if is_same {
+            Ok(())
+        } else {
+            Err(self.diverge(
+                original,
+                formatted,
+                format!(
+                    "expected `{}`, found `{}`",
+                    token_text(original_type),
+                    token_text(formatted_type)
+                ),
+            ))
+        }
+    }
+
+    /// Gets the [`BlockType`] of the passed serialized statement, which is the
+    /// one it's sorted with, if the config sorts it.
+    fn block_type_of(&self, statement: &Value) -> BlockType {
+        statement
+            .get(0)
+            .and_then(|statement| serde_json::from_value::<Statement>(statement.clone()).ok())
+            .map_or(BlockType::None, |statement| get_block_type(&statement, self.config))
+    }
+
+    /// Whether or not `original` and `formatted` have the same statements,
+    /// in any order.
+    fn is_permutation(&mut self, original: &[Value], formatted: &[Value]) -> bool {
+        let mut is_matched = vec![false; formatted.len()];
+
+        original.iter().all(|statement| {
+            let matched = (0..formatted.len())
+                .find(|i| !is_matched[*i] && self.compare(statement, &formatted[*i]).is_ok());
+
+            matched.map(|i| is_matched[i] = true).is_some()
+        })
+    }
+
+    /// Compares two lists of serialized statements. Runs of `require`s, or of
+    /// services, can be in a different order if the config sorts them, as long
+    /// as every statement of the run is one in both lists.
+    fn compare_statements(
+        &mut self,
+        original: &[Value],
+        formatted: &[Value],
+    ) -> Result<(), Divergence> {
+        let mut i = 0;
+
+        while i < original.len().min(formatted.len()) {
+            let Err(divergence) = self.compare_child(format!("[{i}]"), &original[i], &formatted[i])
+            else {
+                i += 1;
+                continue;
+            };
+
+            let block_type = self.block_type_of(&original[i]);
+            let mut sorted_run_end = None;
+            for end in i + 1..=original.len().min(formatted.len()) {
+                if block_type == BlockType::None
+                    || self.block_type_of(&original[end - 1]) != block_type
+                    || self.block_type_of(&formatted[end - 1]) != block_type
+                {
+                    break;
+                }
+                if self.is_permutation(&original[i..end], &formatted[i..end]) {
+                    sorted_run_end = Some(end);
+                    break;
+                }
+            }
+
+            match sorted_run_end {
+                Some(end) => i = end,
+                None => return Err(divergence),
+            }
+        }
+
+        if original.len() == formatted.len() {
+            Ok(())
+        } else {
+            Err(self.diverge(
+                &Value::Array(original.to_vec()),
+                &Value::Array(formatted.to_vec()),
+                format!(
+                    "expected {} statements, found {}",
+                    original.len(),
+                    formatted.len()
+                ),
+            ))
+        }
+    }
+
+    /// Compares two serialized nodes.
+    fn compare(&mut self, original: &Value, formatted: &Value) -> Result<(), Divergence> {
+        match (original, formatted) {
+            (Value::Object(original_map), Value::Object(formatted_map))
+                if original_map.contains_key("token_type")
+                    && formatted_map.contains_key("token_type") =>
+            {
+                self.compare_tokens(original, formatted)
+            }
+            (Value::Object(_), Value::Object(_)) if self.is_split_string(original, formatted) => {
+                Ok(())
+            }
+            (Value::Object(_), Value::Object(_))
+                if self
+                    .path
+                    .last()
+                    .is_some_and(|segment| segment == "arguments")
+                    && variant_of(original) != variant_of(formatted) =>
+            {
+                let original = normalize_arguments(original);
+                let formatted = normalize_arguments(formatted);
+
+                if variant_of(&original) == variant_of(&formatted)
+                    || self.is_split_string(&original, &formatted)
+                {
+                    self.compare(&original, &formatted)
+                } else {
+                    Err(self.diverge(
+                        &original,
+                        &formatted,
+                        "the function arguments are different".to_string(),
+                    ))
+                }
+            }
+            (Value::Object(original_map), Value::Object(formatted_map)) => {
+                if matches!(variant_of(original), Some("Trailing" | "NonTrailing"))
+                    && matches!(variant_of(formatted), Some("Trailing" | "NonTrailing"))
+                {
+                    return self.compare(list_item(original), list_item(formatted));
+                }
+
+                for (key, original_value) in original_map {
+                    if IGNORED_KEYS.contains(&key.as_str()) {
+                        continue;
+                    }
+
+                    let Some(formatted_value) = formatted_map.get(key) else {
+                        return Err(self.diverge(
+                            original,
+                            formatted,
+                            format!(
+                                "expected `{key}`, found `{}`",
+                                variant_of(formatted).unwrap_or("something else")
+                            ),
+                        ));
+                    };
+
+                    match (key.as_str(), original_value, formatted_value) {
+                        ("statements", Value::Array(original), Value::Array(formatted)) => {
+                            self.path.push(key.clone());
+                            let result = self.compare_statements(original, formatted);
+                            self.path.pop();
+
+                            result?
+                        }
+                        // Bracketed items and lists are not worth mentioning.
+                        ("item" | "items", _, _) => {
+                            self.compare(original_value, formatted_value)?
+                        }
+                        _ => self.compare_child(key.clone(), original_value, formatted_value)?,
+                    }
+                }
+
+                Ok(())
+            }
+            (Value::Array(original_values), Value::Array(formatted_values)) => {
+                // A statement and the semicolon after it.
+                if let (
+                    [original_statement, original_semicolon],
+                    [formatted_statement, formatted_semicolon],
+                ) = (original_values.as_slice(), formatted_values.as_slice())
+                    && (original_semicolon.is_null() || is_separator(original_semicolon))
+                    && (formatted_semicolon.is_null() || is_separator(formatted_semicolon))
+                {
+                    return self.compare(original_statement, formatted_statement);
+                }
+
+                if original_values.len() != formatted_values.len() {
+                    return Err(self.diverge(
+                        original,
+                        formatted,
+                        format!(
+                            "expected {} items, found {}",
+                            original_values.len(),
+                            formatted_values.len()
+                        ),
+                    ));
+                }
+
+                for (i, (original_value, formatted_value)) in
+                    original_values.iter().zip(formatted_values).enumerate()
+                {
+                    self.compare_child(format!("[{i}]"), original_value, formatted_value)?;
+                }
+
+                Ok(())
+            }
+            (Value::Null, value) | (value, Value::Null) if is_separator(value) => Ok(()),
+            _ if original == formatted => Ok(()),
+            _ => Err(self.diverge(
+                original,
+                formatted,
+                format!("expected {original}, found {formatted}"),
+            )),
+        }
+    }
+}
+
+/// Parses formatted code, which is a [`Divergence`] if it has syntax errors as
+/// the original code didn't.
+pub(crate) fn parse_formatted_code(formatted_code: &str) -> Result<Pointer<Cst>, Divergence> {
+    let mut parser = Parser::new(formatted_code);
+    let formatted_cst = parser.parse("");
+
+    match syntax_errors(&formatted_cst).first() {
+        Some(error) => Err(Divergence {
+            node: String::new(),
+            original: None,
+            formatted: Some(error.start()),
+            message: format!("the formatted code has a syntax error: {}", error.message()),
+        }),
+        None => Ok(formatted_cst),
+    }
+}
+
+/// Checks that `formatted_code` means the same as the code the passed [`Cst`]
+/// was parsed from. Both are compared node by node, ignoring whitespace and
+/// comments, and allowing the changes the passed [`Config`] asks for, like
+/// changing quotes, removing parentheses around function arguments, and
+/// renaming the local bindings its naming conventions rename, as
+/// [`format_with_config`](crate::format_with_config) does.
+pub fn verify(cst: &Cst, formatted_code: &str, config: &Config) -> Result<(), Divergence> {
+    verify_renamed(cst, formatted_code, &find_renames(&cst.block, config), config)
+}
+
+/// Checks that `formatted_code` means the same as the code the passed [`Cst`]
+/// was parsed from, like [`verify`], where only the identifiers in `renames`,
+/// by their start in the original code, can have another name.
+pub(crate) fn verify_renamed(
+    cst: &Cst,
+    formatted_code: &str,
+    renames: &HashMap<Position, String>,
+    config: &Config,
+) -> Result<(), Divergence> {
+    let formatted_cst = parse_formatted_code(formatted_code)?;
+
+    let original = serialize(&cst.block);
+    let formatted = serialize(&formatted_cst.block);
+
+    Verifier {
+        config,
+        renames,
+        path: Vec::new(),
+    }
+    .compare(&original, &formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use luau_parser::parser::Parser;
+
+    use super::verify;
+    use crate::{Config, NamingConvention};
+
+    /// Checks that only runs of `require`s or services can change order.
+    #[test]
+    fn verify_only_allows_sorting() {
+        let config = Config::default();
+        let cases = [
+            ("local a = 1\nlocal b = a\n", "local b = a\nlocal a = 1\n", false),
+            (
+                "local b = require(B)\nlocal a = require(A)\n",
+                "local a = require(A)\nlocal b = require(B)\n",
+                true,
+            ),
+            (
+                "local b = require(B)\nlocal a = 1\n",
+                "local a = 1\nlocal b = require(B)\n",
+                false,
+            ),
+            (
+                "local B = game:GetService(\"B\")\nlocal a = require(A)\n",
+                "local a = require(A)\nlocal B = game:GetService(\"B\")\n",
+                false,
+            ),
+        ];
+
+        for (original, formatted, is_same) in cases {
+            let mut parser = Parser::new(original);
+
+            assert_eq!(
+                verify(&parser.parse(""), formatted, &config).is_ok(),
+                is_same,
+                "{original:?} -> {formatted:?}"
+            );
+        }
+
+        let unsorted = Config {
+            sort_requires: false,
+            ..Default::default()
+        };
+        let (original, formatted, _) = cases[1];
+        let mut parser = Parser::new(original);
+        assert!(verify(&parser.parse(""), formatted, &unsorted).is_err());
+    }
+
+    /// Checks that only the local bindings the formatter renames can be renamed.
+    #[test]
+    fn verify_only_allows_renaming_locals() {
+        let config = Config {
+            variable_casing: NamingConvention::Snake,
+            ..Default::default()
+        };
+        let cases = [
+            ("local fooBar = 1\nprint(fooBar)\n", "local foo_bar = 1\nprint(foo_bar)\n", true),
+            ("local fooBar = 1\nprint(fooBar)\n", "local fooBar = 1\nprint(fooBar)\n", false),
+            ("print(fooBar)\n", "print(foo_bar)\n", false),
+            ("local t = {}\nt.someField = 1\n", "local t = {}\nt.some_field = 1\n", false),
+        ];
+
+        for (original, formatted, is_same) in cases {
+            let mut parser = Parser::new(original);
+
+            assert_eq!(
+                verify(&parser.parse(""), formatted, &config).is_ok(),
+                is_same,
+                "{original:?} -> {formatted:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! Checks that formatting every file of `tests/corpus` twice gives the same
+//! code, with every value of every option that changes how code is formatted.
+
+use luau_fmt::{
+    CompactTable, Config, FormattingError, FunctionParenthesis, IndentStyle, NamingConvention,
+    QuoteStyle, Semicolon, StringWrap, Target, TrailingCommas, check_idempotency, format_range,
+    format_tolerant, format_with_config,
+};
+use luau_parser::parser::Parser;
+use std::{fs, path::Path};
+
+/// The configs to check each file with, each of which changes one option of
+/// the default config.
+fn configs() -> Vec<(String, Config)> {
+    let mut configs = vec![("default".to_string(), Config::default())];
+
+    for quote_style in [
+        QuoteStyle::Single,
+        QuoteStyle::PreferSingle,
+        QuoteStyle::Double,
+        QuoteStyle::PreferDouble,
+    ] {
+        configs.push((
+            format!("quote_style = {quote_style:?}"),
+            Config {
+                quote_style,
+                ..Default::default()
+            },
+        ));
+    }
+    for compact_table in [
+        CompactTable::Always,
+        CompactTable::OnlyLiterals,
+        CompactTable::SingleElement,
+        CompactTable::Never,
+    ] {
+        configs.push((
+            format!("compact_table = {compact_table:?}"),
+            Config {
+                compact_table,
+                ..Default::default()
+            },
+        ));
+    }
+    for function_parenthesis in [
+        FunctionParenthesis::Always,
+        FunctionParenthesis::Keep,
+        FunctionParenthesis::RemoveForStrings,
+        FunctionParenthesis::RemoveForTables,
+        FunctionParenthesis::RemoveWhenPossible,
+    ] {
+        configs.push((
+            format!("function_parenthesis = {function_parenthesis:?}"),
+            Config {
+                function_parenthesis,
+                ..Default::default()
+            },
+        ));
+    }
+    for semicolon in [Semicolon::Never, Semicolon::Always, Semicolon::Keep] {
+        configs.push((
+            format!("semicolon = {semicolon:?}"),
+            Config {
+                semicolon,
+                ..Default::default()
+            },
+        ));
+    }
+    for trailing_commas in [
+        TrailingCommas::Always,
+        TrailingCommas::Never,
+        TrailingCommas::OnlyMultiLine,
+    ] {
+        configs.push((
+            format!("trailing_commas = {trailing_commas:?}"),
+            Config {
+                trailing_commas,
+                ..Default::default()
+            },
+        ));
+    }
+
+    for trailing_commas in [TrailingCommas::Always, TrailingCommas::OnlyMultiLine] {
+        configs.push((
+            format!("magic_trailing_comma, trailing_commas = {trailing_commas:?}"),
+            Config {
+                magic_trailing_comma: true,
+                trailing_commas,
+                ..Default::default()
+            },
+        ));
+    }
+
+    configs.push((
+        "interpolation_padding".to_string(),
+        Config {
+            interpolation_padding: true,
+            ..Default::default()
+        },
+    ));
+    configs.push((
+        "tabs".to_string(),
+        Config {
+            indent_style: IndentStyle::Tabs,
+            ..Default::default()
+        },
+    ));
+    configs.push((
+        "narrow".to_string(),
+        Config {
+            column_width: 40,
+            string_width: 20,
+            comments_width: 30,
+            ..Default::default()
+        },
+    ));
+    for string_wrap in [StringWrap::Concatenation, StringWrap::Never] {
+        configs.push((
+            format!("narrow, string_wrap = {string_wrap:?}"),
+            Config {
+                column_width: 40,
+                string_width: 20,
+                string_wrap,
+                ..Default::default()
+            },
+        ));
+    }
+    configs.push((
+        "narrow, target = Lua51".to_string(),
+        Config {
+            column_width: 40,
+            string_width: 20,
+            target: Target::Lua51,
+            ..Default::default()
+        },
+    ));
+    configs.push((
+        "keep_statements_spacing".to_string(),
+        Config {
+            keep_statements_spacing: true,
+            ..Default::default()
+        },
+    ));
+    for naming_convention in [
+        NamingConvention::Camel,
+        NamingConvention::Pascal,
+        NamingConvention::Snake,
+        NamingConvention::ScreamingSnake,
+    ] {
+        configs.push((
+            format!("casing = {naming_convention:?}"),
+            Config {
+                variable_casing: naming_convention,
+                type_casing: naming_convention,
+                ..Default::default()
+            },
+        ));
+    }
+    configs.push((
+        "unsorted".to_string(),
+        Config {
+            sort_requires: false,
+            sort_services: false,
+            ..Default::default()
+        },
+    ));
+
+    configs
+}
+
+/// The code and path of every file of the corpus.
+fn corpus() -> Vec<(String, String)> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut files = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "luau")
+        })
+        .map(|path| {
+            (
+                path.display().to_string(),
+                fs::read_to_string(&path).unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    files.sort();
+    files
+}
+
+/// Describes why `path` isn't formatted idempotently with the config `name`.
+fn describe(path: &str, name: &str, error: FormattingError) -> String {
+    match error {
+        FormattingError::NotIdempotent {
+            first_pass,
+            second_pass,
+        } => format!(
+            "{path} ({name}) isn't formatted idempotently:\n{}",
+            similar::TextDiff::from_lines(&first_pass, &second_pass)
+                .unified_diff()
+                .header("first pass", "second pass")
+        ),
+        error => format!("{path} ({name}) couldn't be formatted: {error}"),
+    }
+}
+
+/// Formats every file with every config twice.
+#[test]
+fn formatting_is_idempotent() {
+    let mut failures = Vec::new();
+
+    for (path, code) in corpus() {
+        let mut parser = Parser::new(&code);
+        let cst = parser.parse(&path);
+
+        for (name, config) in configs() {
+            let config = Config {
+                verify: true,
+                ..config
+            };
+
+            if let Err(error) = check_idempotency(&cst, &config) {
+                failures.push(describe(&path, &name, error));
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+/// Formats every file twice with a syntax error at its end.
+#[test]
+fn tolerant_formatting_is_idempotent() {
+    let mut failures = Vec::new();
+
+    for (path, code) in corpus() {
+        let broken = format!("{code}\nlocal broken = = 1\n");
+        let mut parser = Parser::new(&broken);
+        let cst = parser.parse(&path);
+
+        let config = Config::default();
+        let first_pass = format_tolerant(&cst, &broken, &config);
+
+        let mut parser = Parser::new(&first_pass);
+        let second_pass = format_tolerant(&parser.parse(&path), &first_pass, &config);
+
+        if first_pass != second_pass {
+            failures.push(describe(
+                &path,
+                "tolerant",
+                FormattingError::NotIdempotent {
+                    first_pass,
+                    second_pass,
+                },
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+/// Formats every file twice as a range covering all of it.
+#[test]
+fn range_formatting_is_idempotent() {
+    let mut failures = Vec::new();
+
+    for (path, code) in corpus() {
+        let config = Config::default();
+        let format_all = |code: &str| {
+            let mut parser = Parser::new(code);
+            format_range(&parser.parse(&path), &config, 0..code.len())
+        };
+
+        let result = format_all(&code).and_then(|first_pass| {
+            let second_pass = format_all(&first_pass)?;
+
+            if first_pass == second_pass {
+                Ok(())
+            } else {
+                Err(FormattingError::NotIdempotent {
+                    first_pass,
+                    second_pass,
+                })
+            }
+        });
+
+        if let Err(error) = result {
+            failures.push(describe(&path, "range", error));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+/// Checks that formatting the second half of every file, then all of it, gives
+/// the same code as formatting all of it directly.
+#[test]
+fn range_formatting_matches_formatting() {
+    let mut failures = Vec::new();
+
+    for (path, code) in corpus() {
+        let config = Config::default();
+        let middle = (code.len() / 2..)
+            .find(|&i| code.is_char_boundary(i))
+            .unwrap();
+
+        let mut parser = Parser::new(&code);
+        let cst = parser.parse(&path);
+        let formatted = format_with_config(&cst, &config);
+        let partially_formatted = format_range(&cst, &config, middle..code.len());
+
+        let result = partially_formatted.and_then(|partially_formatted| {
+            let mut parser = Parser::new(&partially_formatted);
+            format_with_config(&parser.parse(&path), &config)
+        });
+
+        if result != formatted {
+            failures.push(format!(
+                "{path} is formatted differently after formatting its second half: \
+                {result:?} instead of {formatted:?}"
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}